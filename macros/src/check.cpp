@@ -0,0 +1,31 @@
+#include <lauxlib.h>
+#include <lua.h>
+
+#include <string.h>
+
+// Returns false if the chunk has a syntax error or the check cannot be done, in which case err
+// will contain the message.
+extern "C" bool zlm_check(const char *name, const char *src, size_t len, char *err, size_t errlen)
+{
+    auto L = luaL_newstate();
+
+    if (!L) {
+        strncpy(err, "couldn't create lua_State", errlen - 1);
+        err[errlen - 1] = 0;
+        return false;
+    }
+
+    // Compile.
+    auto r = luaL_loadbufferx(L, src, len, name, "t") == LUA_OK;
+
+    if (!r) {
+        auto m = lua_tostring(L, -1);
+
+        strncpy(err, m ? m : "unknown error", errlen - 1);
+        err[errlen - 1] = 0;
+    }
+
+    lua_close(L);
+
+    return r;
+}
@@ -0,0 +1,33 @@
+use std::ffi::{CStr, c_char};
+
+/// Compile `src` with the vendored Lua and returns the error message if it has a syntax error.
+pub fn check(name: &CStr, src: &[u8]) -> Result<(), String> {
+    let mut err = [0; 1024];
+    let ok = unsafe {
+        zlm_check(
+            name.as_ptr(),
+            src.as_ptr().cast(),
+            src.len(),
+            err.as_mut_ptr(),
+            err.len(),
+        )
+    };
+
+    if ok {
+        Ok(())
+    } else {
+        Err(unsafe { CStr::from_ptr(err.as_ptr()) }
+            .to_string_lossy()
+            .into_owned())
+    }
+}
+
+unsafe extern "C" {
+    fn zlm_check(
+        name: *const c_char,
+        src: *const c_char,
+        len: usize,
+        err: *mut c_char,
+        errlen: usize,
+    ) -> bool;
+}
@@ -295,20 +295,73 @@ fn parse_fn(
             .push(parse_quote!(#[allow(clippy::new_ret_no_self)]));
     }
 
+    // Get receiver.
+    let recv = match f.sig.receiver() {
+        Some(r) if r.reference.is_none() || r.colon_token.is_some() => {
+            return Err(Error::new_spanned(r, "expect &self or &mut self"));
+        }
+        Some(r) => Some(r.mutability.is_some()),
+        None => None,
+    };
+
+    if recv.is_some() {
+        match ty {
+            FnType::AsyncMethod => {
+                return Err(Error::new_spanned(
+                    &f.sig,
+                    "async method with self is not supported",
+                ));
+            }
+            FnType::ClassMethod => {
+                return Err(Error::new_spanned(&f.sig, "class method cannot have self"));
+            }
+            FnType::Property if recv == Some(true) => {
+                return Err(Error::new_spanned(
+                    f.sig.receiver(),
+                    "property cannot have &mut self",
+                ));
+            }
+            _ => (),
+        }
+    }
+
     // Get function name.
     let span = Span::call_site();
     let name = ident.to_string();
+    let (this, arg) = match recv {
+        Some(true) => (
+            quote! { let mut this = cx.to_ud_mut::<Self>(::zl::PositiveInt::ONE); },
+            quote! { &mut this },
+        ),
+        Some(false) => (
+            quote! { let this = cx.to_ud::<Self>(::zl::PositiveInt::ONE).into_ud(); },
+            quote! { this },
+        ),
+        None => (TokenStream::new(), TokenStream::new()),
+    };
+    let func = match recv {
+        Some(_) => quote! { |cx| { #this Self::#ident(#arg, cx) } },
+        None => quote! { Self::#ident },
+    };
 
     match ty {
         FnType::Method => index.extend(quote_spanned! {span=>
-            #name => drop(cx.push_fn(Self::#ident)),
+            #name => drop(cx.push_fn(#func)),
         }),
         FnType::AsyncMethod => index.extend(quote_spanned! {span=>
             #name => drop(cx.push_async(Self::#ident)),
         }),
-        FnType::Property => index.extend(quote_spanned! {span=>
-            #name => return Self::#ident(cx),
-        }),
+        FnType::Property => match recv {
+            Some(_) => index.extend(quote_spanned! {span=>
+                #name => {
+                    #this
+                    return Self::#ident(#arg, cx);
+                }
+            }),
+            None => index.extend(quote_spanned! {span=>
+                #name => return Self::#ident(cx),
+            }),
+        },
         FnType::ClassMethod => {
             let name = CString::new(name).unwrap();
             let name = LitCStr::new(&name, Span::call_site());
@@ -319,7 +372,7 @@ fn parse_fn(
         }
         FnType::Close => {
             *close = quote! {
-                meta.set(c"__close").push_fn(Self::#ident);
+                meta.set(c"__close").push_fn(#func);
             }
         }
     }
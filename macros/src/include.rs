@@ -0,0 +1,51 @@
+use crate::check::check;
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use std::ffi::CString;
+use std::path::PathBuf;
+use syn::{Error, LitCStr, LitStr};
+
+pub fn lua(path: LitStr) -> syn::Result<TokenStream> {
+    // Resolve path relative to the file that invoke the macro, same as include_bytes.
+    let name = path.value();
+    let file = match proc_macro::Span::call_site().local_file() {
+        Some(v) => v
+            .parent()
+            .map(|v| v.join(&name))
+            .unwrap_or_else(|| name.clone().into()),
+        None => PathBuf::from(std::env::var_os("CARGO_MANIFEST_DIR").unwrap()).join(&name),
+    };
+
+    // Read the source.
+    let src = match std::fs::read(&file) {
+        Ok(v) => v,
+        Err(e) => {
+            let m = format!("couldn't read {}: {}", file.display(), e);
+            return Err(Error::new_spanned(path, m));
+        }
+    };
+
+    // Check syntax.
+    let chunk = match CString::new(format!("@{name}")) {
+        Ok(v) => v,
+        Err(_) => return Err(Error::new_spanned(path, "path cannot contains NUL")),
+    };
+
+    if let Err(e) = check(&chunk, &src) {
+        return Err(Error::new_spanned(path, e));
+    }
+
+    // Use include_bytes so Cargo will rebuild when the file changed. The path must be absolute
+    // otherwise include_bytes will resolve it relative to the current file again.
+    let file = std::path::absolute(&file).unwrap_or(file);
+    let file = match file.to_str() {
+        Some(v) => LitStr::new(v, path.span()),
+        None => return Err(Error::new_spanned(path, "path is not a valid UTF-8")),
+    };
+
+    let chunk = LitCStr::new(&chunk, Span::call_site());
+
+    Ok(quote! {
+        ::zl::LuaSource::new(#chunk, include_bytes!(#file))
+    })
+}
@@ -1,8 +1,14 @@
 use proc_macro::TokenStream;
-use syn::{Error, Item, ItemEnum, ItemImpl, parse_macro_input};
+use syn::{Error, Item, ItemEnum, ItemImpl, LitStr, parse_macro_input};
 
+mod check;
 mod class;
 mod derive;
+mod include;
+mod module;
+mod snippet;
+
+extern crate zl_sys; // Required since no Rust code references this crate.
 
 #[proc_macro_attribute]
 pub fn class(arg: TokenStream, item: TokenStream) -> TokenStream {
@@ -17,6 +23,55 @@ pub fn class(arg: TokenStream, item: TokenStream) -> TokenStream {
         .into()
 }
 
+#[proc_macro_attribute]
+pub fn module(arg: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as Item);
+    let mut opts = self::module::Options::default();
+    let parser = syn::meta::parser(|m| opts.parse(m));
+
+    parse_macro_input!(arg with parser);
+
+    self::module::transform(item, opts)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Embeds a Lua source file and checks its syntax at compile time.
+///
+/// The path is resolved relative to the current file, the same as [`include_bytes!`]. This
+/// expands to `zl::LuaSource`, which can be passed to `Frame::load()`.
+#[proc_macro]
+pub fn include_lua(arg: TokenStream) -> TokenStream {
+    let path = parse_macro_input!(arg as LitStr);
+
+    self::include::lua(path)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Runs a Lua snippet on a frame.
+///
+/// The first argument is a mutable reference to the frame followed by the snippet. `$name` will be
+/// replaced by the value of a Rust variable `name`, which must implement `IntoLua`. The results
+/// are converted with `FromLua`. The snippet is checked for syntax errors at compile time and
+/// compiled once per call site.
+///
+/// The snippet is read as Rust tokens so single-quoted strings with more than one character, long
+/// brackets and `//` (which Rust treat as a comment) cannot be used.
+///
+/// ```ignore
+/// let v: i64 = lua!(&mut lua, return $a + $b)?;
+/// ```
+#[proc_macro]
+pub fn lua(arg: TokenStream) -> TokenStream {
+    let arg = parse_macro_input!(arg as self::snippet::Input);
+    let file = proc_macro::Span::call_site().file();
+
+    self::snippet::transform(arg, file)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
 #[proc_macro_derive(FromOption)]
 pub fn derive_from_option(item: TokenStream) -> TokenStream {
     let item = parse_macro_input!(item as ItemEnum);
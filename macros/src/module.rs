@@ -0,0 +1,197 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned};
+use std::ffi::CString;
+use syn::meta::ParseNestedMeta;
+use syn::{Error, Ident, ImplItem, Item, ItemImpl, ItemMod, LitCStr, LitStr, Type, Visibility};
+
+pub fn transform(item: Item, opts: Options) -> syn::Result<TokenStream> {
+    let name = match opts.name {
+        Some(v) => v,
+        None => return Err(Error::new(Span::call_site(), "missing module name")),
+    };
+
+    match item {
+        Item::Impl(v) => transform_impl(v, name, opts.global),
+        Item::Mod(v) => transform_mod(v, name, opts.global),
+        v => Err(Error::new_spanned(v, "expect an impl block or a mod")),
+    }
+}
+
+fn transform_impl(item: ItemImpl, name: LitStr, global: bool) -> syn::Result<TokenStream> {
+    // Check impl block.
+    if let Some(v) = &item.defaultness {
+        return Err(Error::new_spanned(
+            v,
+            "default implementation is not supported",
+        ));
+    }
+
+    if let Some(v) = &item.unsafety {
+        return Err(Error::new_spanned(
+            v,
+            "unsafe implementation is not supported",
+        ));
+    }
+
+    if item.generics.lt_token.is_some() {
+        return Err(Error::new_spanned(
+            item.generics,
+            "generic implementation is not supported",
+        ));
+    }
+
+    if let Some((_, v, _)) = &item.trait_ {
+        return Err(Error::new_spanned(
+            v,
+            "trait implementation cannot be used here",
+        ));
+    }
+
+    // Get type.
+    let ty = match item.self_ty.as_ref() {
+        Type::Path(v) => v.path.require_ident()?,
+        v => return Err(Error::new_spanned(v, "unsupported type")),
+    };
+
+    // Parse items. Only public items are exported the same as mod.
+    let mut fields = Vec::new();
+
+    for i in &item.items {
+        match i {
+            ImplItem::Const(v) if is_exported(&v.vis) => fields.push(Field::Const(&v.ident)),
+            ImplItem::Fn(v) if is_exported(&v.vis) => match v.sig.asyncness {
+                Some(_) => fields.push(Field::Async(&v.sig.ident)),
+                None => fields.push(Field::Fn(&v.sig.ident)),
+            },
+            ImplItem::Const(_) | ImplItem::Fn(_) => {}
+            i => return Err(Error::new_spanned(i, "unsupported item")),
+        }
+    }
+
+    let register = gen_register(&name, global, &fields, quote!(Self::));
+
+    Ok(quote! {
+        #item
+
+        impl #ty {
+            #register
+        }
+    })
+}
+
+fn transform_mod(mut item: ItemMod, name: LitStr, global: bool) -> syn::Result<TokenStream> {
+    let items = match &mut item.content {
+        Some((_, v)) => v,
+        None => {
+            return Err(Error::new_spanned(
+                item,
+                "non-inline module is not supported",
+            ));
+        }
+    };
+
+    // Parse items. Only public items are exported so the module can have private helpers.
+    let mut fields = Vec::new();
+
+    for i in items.iter() {
+        match i {
+            Item::Const(v) if is_exported(&v.vis) => fields.push(Field::Const(&v.ident)),
+            Item::Fn(v) if is_exported(&v.vis) => match v.sig.asyncness {
+                Some(_) => fields.push(Field::Async(&v.sig.ident)),
+                None => fields.push(Field::Fn(&v.sig.ident)),
+            },
+            _ => {}
+        }
+    }
+
+    let register = gen_register(&name, global, &fields, TokenStream::new());
+    let register = syn::parse2(register)?;
+
+    items.push(register);
+
+    Ok(quote!(#item))
+}
+
+fn gen_register(name: &LitStr, global: bool, fields: &[Field], prefix: TokenStream) -> TokenStream {
+    let name = CString::new(name.value()).unwrap();
+    let name = LitCStr::new(&name, Span::call_site());
+    let span = Span::call_site();
+    let n = u16::try_from(fields.len()).unwrap();
+    let mut setters = TokenStream::new();
+
+    for f in fields {
+        let ident = f.ident();
+        let key = CString::new(ident.to_string()).unwrap();
+        let key = LitCStr::new(&key, Span::call_site());
+
+        setters.extend(match f {
+            Field::Const(_) => quote_spanned! {span=>
+                ::zl::IntoLua::into_lua(#prefix #ident, &mut t.set(#key));
+            },
+            Field::Fn(_) => quote_spanned! {span=>
+                t.set(#key).push_fn(#prefix #ident);
+            },
+            Field::Async(_) => quote_spanned! {span=>
+                t.set(#key).push_async(#prefix #ident);
+            },
+        });
+    }
+
+    quote! {
+        /// Register this module to `package.loaded`.
+        ///
+        /// Returns `false` if the module with the same name already loaded.
+        pub fn register<P: ::zl::Frame>(p: &mut P) -> bool {
+            use ::zl::Frame;
+
+            let mut m = match p.register_module(#name, #global) {
+                Some(v) => v,
+                None => return false,
+            };
+
+            let mut t = m.push_table(0, #n);
+
+            #setters
+
+            true
+        }
+    }
+}
+
+fn is_exported(vis: &Visibility) -> bool {
+    !matches!(vis, Visibility::Inherited)
+}
+
+#[derive(Default)]
+pub struct Options {
+    name: Option<LitStr>,
+    global: bool,
+}
+
+impl Options {
+    pub fn parse(&mut self, m: ParseNestedMeta) -> syn::Result<()> {
+        if m.path.is_ident("name") {
+            self.name = Some(m.value()?.parse()?);
+        } else if m.path.is_ident("global") {
+            self.global = true;
+        } else {
+            return Err(m.error("unknown option"));
+        }
+
+        Ok(())
+    }
+}
+
+enum Field<'a> {
+    Const(&'a Ident),
+    Fn(&'a Ident),
+    Async(&'a Ident),
+}
+
+impl Field<'_> {
+    fn ident(&self) -> &Ident {
+        match self {
+            Self::Const(v) | Self::Fn(v) | Self::Async(v) => v,
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use crate::check::check;
+use proc_macro2::{Delimiter, Ident, Spacing, Span, TokenStream, TokenTree};
+use quote::quote;
+use std::ffi::CString;
+use syn::parse::{Parse, ParseStream};
+use syn::{Error, Expr, LitByteStr, LitCStr, Token};
+
+pub fn transform(input: Input, file: String) -> syn::Result<TokenStream> {
+    // Build Lua source. Captured variables are passed as chunk arguments on the first line so line
+    // numbers are the same as the Rust file.
+    let mut w = Writer::default();
+
+    w.write(input.code)?;
+
+    let mut code = String::new();
+
+    if !w.caps.is_empty() {
+        let names: Vec<String> = w.caps.iter().map(|v| v.to_string()).collect();
+
+        code.push_str("local ");
+        code.push_str(&names.join(", "));
+        code.push_str(" = ...;");
+    }
+
+    code.push_str(&w.code);
+
+    // Check syntax.
+    let name = match CString::new(format!("@{file}")) {
+        Ok(v) => v,
+        Err(_) => return Err(Error::new(Span::call_site(), "file name contains NUL")),
+    };
+
+    if let Err(e) = check(&name, code.as_bytes()) {
+        let span = error_line(&e)
+            .and_then(|l| w.lines.iter().find(|v| v.0 == l))
+            .map(|v| v.1)
+            .unwrap_or_else(Span::call_site);
+
+        return Err(Error::new(span, e));
+    }
+
+    // Generate Rust code.
+    let frame = input.frame;
+    let name = LitCStr::new(&name, Span::call_site());
+    let code = LitByteStr::new(code.as_bytes(), Span::call_site());
+    let caps = w.caps;
+
+    Ok(quote! {
+        {
+            static SITE: u8 = 0;
+
+            ::zl::run_snippet(#frame, &SITE, #name, #code, |f| {
+                #(::zl::push_capture(f, #caps);)*
+            })
+        }
+    })
+}
+
+/// Returns line number from a Lua error message in the form of `chunkid:line: message`.
+///
+/// We can't match on the file name since Lua truncate the chunk id to `LUA_IDSIZE`.
+fn error_line(e: &str) -> Option<usize> {
+    let mut parts = e.split(':').skip(1).peekable();
+
+    while let Some(v) = parts.next() {
+        if parts.peek().is_none() {
+            break;
+        }
+
+        if let Ok(v) = v.parse() {
+            return Some(v);
+        }
+    }
+
+    None
+}
+
+/// Input of `lua` macro.
+pub struct Input {
+    frame: Expr,
+    code: TokenStream,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let frame = input.parse()?;
+
+        input.parse::<Token![,]>()?;
+
+        Ok(Self {
+            frame,
+            code: input.parse()?,
+        })
+    }
+}
+
+/// Struct to convert Rust tokens back to Lua source.
+#[derive(Default)]
+struct Writer {
+    code: String,
+    line: usize,
+    joint: bool,
+    caps: Vec<Ident>,
+    lines: Vec<(usize, Span)>,
+}
+
+impl Writer {
+    fn write(&mut self, tokens: TokenStream) -> syn::Result<()> {
+        let mut iter = tokens.into_iter();
+
+        while let Some(t) = iter.next() {
+            match t {
+                TokenTree::Group(g) => {
+                    let (open, close) = match g.delimiter() {
+                        Delimiter::Parenthesis => ("(", ")"),
+                        Delimiter::Brace => ("{", "}"),
+                        Delimiter::Bracket => ("[", "]"),
+                        Delimiter::None => ("", ""),
+                    };
+
+                    self.token(g.span_open(), open);
+                    self.write(g.stream())?;
+                    self.token(g.span_close(), close);
+                }
+                TokenTree::Ident(v) => self.token(v.span(), &v.to_string()),
+                TokenTree::Punct(v) if v.as_char() == '$' => {
+                    let id = match iter.next() {
+                        Some(TokenTree::Ident(v)) => v,
+                        _ => return Err(Error::new(v.span(), "expect a variable name after $")),
+                    };
+
+                    self.token(id.span(), &id.to_string());
+
+                    if !self.caps.contains(&id) {
+                        self.caps.push(id);
+                    }
+                }
+                TokenTree::Punct(v) => {
+                    self.token(v.span(), v.as_char().encode_utf8(&mut [0; 4]));
+                    self.joint = v.spacing() == Spacing::Joint;
+                }
+                TokenTree::Literal(v) => self.token(v.span(), &v.to_string()),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn token(&mut self, span: Span, s: &str) {
+        if s.is_empty() {
+            return;
+        }
+
+        // Keep the token on the same line as Rust source.
+        let line = span.start().line;
+
+        if self.line == 0 {
+            self.line = 1;
+        }
+
+        if line > self.line {
+            for _ in self.line..line {
+                self.code.push('\n');
+            }
+
+            self.line = line;
+            self.lines.push((line, span));
+        } else if !self.joint && !self.code.is_empty() {
+            self.code.push(' ');
+        }
+
+        self.joint = false;
+        self.code.push_str(s);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line() {
+        assert_eq!(error_line("src/main.rs:12: unexpected symbol"), Some(12));
+        assert_eq!(
+            error_line("...very/long/path/main.rs:7: 'end' expected"),
+            Some(7)
+        );
+        assert_eq!(error_line("C:\\src\\main.rs:3: error: 5: x"), Some(3));
+        assert_eq!(error_line("not enough memory"), None);
+    }
+}
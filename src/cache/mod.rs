@@ -0,0 +1,322 @@
+use crate::ffi::{zl_load, zl_pop, zl_version};
+use crate::transform::{display_name, set_lines};
+use crate::{ChunkType, Frame, Function, SourceTransformer, Str};
+use std::ffi::CStr;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAGIC: &[u8; 4] = b"ZLC\0";
+
+/// Storage of compiled chunks for [`Lua::set_chunk_cache()`](crate::Lua::set_chunk_cache()).
+///
+/// The implementation does not need to validate the data since it will be validated when loaded.
+pub trait ChunkStore: 'static {
+    /// Returns the data that was stored with `key`.
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>>;
+
+    /// Store `data` with `key`, replacing the previous data.
+    ///
+    /// The implementation should ignore any error since the cache is optional.
+    fn put(&self, key: &[u8], data: &[u8]);
+}
+
+/// [`ChunkStore`] that store each chunk as a file in a directory.
+///
+/// The directory will be created when the first chunk is stored.
+pub struct DirStore {
+    dir: PathBuf,
+}
+
+impl DirStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, key: &[u8]) -> PathBuf {
+        self.dir.join(format!("{:016x}.luac", fnv1a(key)))
+    }
+}
+
+impl ChunkStore for DirStore {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        std::fs::read(self.path(key)).ok()
+    }
+
+    fn put(&self, key: &[u8], data: &[u8]) {
+        // The temporary file must be unique since the other threads or processes may store the
+        // same chunk at the same time.
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+        let path = self.path(key);
+        let id = NEXT.fetch_add(1, Ordering::Relaxed);
+        let tmp = path.with_extension(format!("{}.{id}.tmp", std::process::id()));
+
+        // Write to a temporary file first so other processes never see a partial file.
+        let r = std::fs::create_dir_all(&self.dir)
+            .and_then(|_| std::fs::write(&tmp, data))
+            .and_then(|_| std::fs::rename(&tmp, &path));
+
+        if r.is_err() {
+            std::fs::remove_file(&tmp).ok();
+        }
+    }
+}
+
+/// Load a chunk from the cache or compile it and put the result to the cache.
+///
+/// `mtime` is a modification time of the source if available. This also run
+/// [`SourceTransformer`](crate::SourceTransformer) on `data` if there is a matched one.
+pub(crate) fn load_cached<'a, P: Frame>(
+    p: &'a mut P,
+    name: &CStr,
+    mtime: Option<SystemTime>,
+    ty: ChunkType,
+    data: &[u8],
+) -> Result<Function<'a, P>, Str<'a, P>> {
+    let binary = ty == ChunkType::Binary || data.first() == Some(&0x1b);
+    let tf = match binary {
+        true => None,
+        false => crate::transform::find(p.extra1(), name, data),
+    };
+
+    // Get store.
+    let store = p.extra1().cache.borrow().clone();
+    let store = match store {
+        Some(v) if !binary => v,
+        _ => return compile(p, name, ty, data, tf.as_deref()).map(|v| v.0),
+    };
+
+    // Build header.
+    let key = name.to_bytes();
+    let mut header = Vec::with_capacity(41 + key.len());
+    let mtime = mtime
+        .and_then(|v| v.duration_since(UNIX_EPOCH).ok())
+        .map(|v| v.as_nanos())
+        .unwrap_or(0);
+
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&zl_version().to_le_bytes());
+    header.extend_from_slice(&fnv1a(data).to_le_bytes());
+    header.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    header.extend_from_slice(&mtime.to_le_bytes());
+    header.push(tf.is_some().into());
+    header.extend_from_slice(&(key.len() as u64).to_le_bytes());
+    header.extend_from_slice(key);
+
+    // Load from the cache. Lua will reject the bytecode from a different version or build.
+    if let Some((lines, code)) = store
+        .get(key)
+        .and_then(|v| v.strip_prefix(header.as_slice()).and_then(parse_entry))
+    {
+        set_lines(p, name, lines);
+
+        let ok = unsafe {
+            zl_load(
+                p.state(),
+                name.as_ptr(),
+                code.as_ptr().cast(),
+                code.len(),
+                c"b".as_ptr(),
+            )
+        };
+
+        if ok {
+            return Ok(unsafe { Function::new(p) });
+        }
+
+        unsafe { zl_pop(p.state(), 1) };
+    }
+
+    // Compile and store.
+    let (mut f, lines) = compile(p, name, ty, data, tf.as_deref())?;
+
+    if let Some(v) = f.dump(false) {
+        header.extend_from_slice(&(lines.len() as u64).to_le_bytes());
+
+        for &l in lines.iter() {
+            header.extend_from_slice(&l.to_le_bytes());
+        }
+
+        header.extend_from_slice(&v);
+        store.put(key, &header);
+    }
+
+    Ok(f)
+}
+
+/// Returns line mapping and bytecode from the cache entry without header.
+fn parse_entry(data: &[u8]) -> Option<(Rc<[u32]>, Vec<u8>)> {
+    let (n, data) = data.split_first_chunk::<8>()?;
+    let n = usize::try_from(u64::from_le_bytes(*n)).ok()?;
+    let (lines, code) = data.split_at_checked(n.checked_mul(4)?)?;
+    let lines = lines
+        .chunks_exact(4)
+        .map(|v| u32::from_le_bytes(v.try_into().unwrap()))
+        .collect();
+
+    Some((lines, code.to_vec()))
+}
+
+/// Run `tf` on `data` if not [`None`] then load the result.
+fn compile<'a, P: Frame>(
+    p: &'a mut P,
+    name: &CStr,
+    ty: ChunkType,
+    data: &[u8],
+    tf: Option<&dyn SourceTransformer>,
+) -> Result<Compiled<'a, P>, Str<'a, P>> {
+    let tf = match tf {
+        Some(v) => v,
+        None => {
+            // Clear the mapping from the previous load of the same chunk.
+            set_lines(p, name, Rc::from([]));
+
+            return p.load(Some(name), ty, data).map(|f| (f, Rc::from([])));
+        }
+    };
+
+    // Transform.
+    let display = display_name(name);
+    let t = match tf.transform(&display, data) {
+        Ok(v) => v,
+        Err(e) => return Err(p.push_str(format!("{display}: {e}"))),
+    };
+
+    // Load.
+    let lines: Rc<[u32]> = t.lines.into();
+
+    set_lines(p, name, lines.clone());
+
+    p.load(Some(name), ChunkType::Text, t.code)
+        .map(|f| (f, lines))
+}
+
+/// Loaded function and its line mapping.
+type Compiled<'a, P> = (Function<'a, P>, Rc<[u32]>);
+
+/// 64-bit FNV-1a, which is stable across Rust versions unlike [`std::hash::DefaultHasher`].
+fn fnv1a(data: &[u8]) -> u64 {
+    let mut h = 0xcbf29ce484222325u64;
+
+    for &b in data {
+        h ^= u64::from(b);
+        h = h.wrapping_mul(0x100000001b3);
+    }
+
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lua;
+    use std::cell::{Cell, RefCell};
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    #[derive(Default, Clone)]
+    struct Memory {
+        data: Rc<RefCell<HashMap<Vec<u8>, Vec<u8>>>>,
+        puts: Rc<Cell<usize>>,
+    }
+
+    impl ChunkStore for Memory {
+        fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+            self.data.borrow().get(key).cloned()
+        }
+
+        fn put(&self, key: &[u8], data: &[u8]) {
+            self.data.borrow_mut().insert(key.into(), data.into());
+            self.puts.set(self.puts.get() + 1);
+        }
+    }
+
+    fn run(store: &Memory, src: &[u8]) -> i64 {
+        let mut lua = Lua::new(None).unwrap();
+
+        unsafe { lua.set_chunk_cache(store.clone()) };
+
+        let f = load_cached(&mut lua, c"=main", None, ChunkType::Text, src).unwrap();
+        let mut r = f.call().unwrap();
+
+        r.to_int(1).unwrap()
+    }
+
+    #[test]
+    fn dir() {
+        let dir = std::env::temp_dir().join(format!("zl-cache-{}", std::process::id()));
+        let store = DirStore::new(&dir);
+
+        assert!(store.get(b"=main").is_none());
+        assert!(!dir.exists());
+
+        store.put(b"=main", b"abc");
+        store.put(b"=other", b"def");
+
+        assert_eq!(store.get(b"=main").as_deref(), Some(b"abc".as_slice()));
+        assert_eq!(store.get(b"=other").as_deref(), Some(b"def".as_slice()));
+
+        store.put(b"=main", b"xyz");
+
+        assert_eq!(store.get(b"=main").as_deref(), Some(b"xyz".as_slice()));
+
+        // Concurrent writers must not see each other temporary file.
+        std::thread::scope(|s| {
+            for i in 0..8 {
+                let store = &store;
+
+                s.spawn(move || {
+                    for _ in 0..32 {
+                        store.put(b"=main", &[i; 4096]);
+                    }
+                });
+            }
+        });
+
+        let data = store.get(b"=main").unwrap();
+
+        assert_eq!(data.len(), 4096);
+        assert!(data.iter().all(|&b| b == data[0]));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+        // Check the chunk from the directory.
+        let mut lua = Lua::new(None).unwrap();
+
+        unsafe { lua.set_chunk_cache(DirStore::new(&dir)) };
+
+        for _ in 0..2 {
+            let f = load_cached(&mut lua, c"=main", None, ChunkType::Text, b"return 3").unwrap();
+            let mut r = f.call().unwrap();
+
+            assert_eq!(r.to_int(1), Some(3));
+        }
+
+        assert!(store.get(b"=main").unwrap().starts_with(MAGIC));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn memory() {
+        let store = Memory::default();
+
+        assert_eq!(run(&store, b"return 1"), 1);
+        assert_eq!(store.puts.get(), 1);
+        assert_eq!(run(&store, b"return 1"), 1);
+        assert_eq!(store.puts.get(), 1);
+
+        // Check invalidation.
+        assert_eq!(run(&store, b"return 2"), 2);
+        assert_eq!(store.puts.get(), 2);
+
+        // Check corrupted entry.
+        for v in store.data.borrow_mut().values_mut() {
+            v.truncate(v.len() - 4);
+        }
+
+        assert_eq!(run(&store, b"return 2"), 2);
+        assert_eq!(store.puts.get(), 3);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::ffi::{zl_pushinteger, zl_pushnumber};
+use crate::state::RawState;
+use crate::{Context, Error};
+
+/// Names of standard library functions that are still nondeterministic in deterministic mode.
+///
+/// - `os.time` with a table depends on the local time zone.
+/// - `os.getenv`, `os.execute`, `os.remove`, `os.rename`, `os.tmpname` and `os.setlocale` access
+///   the host. Use [`Frame::require_safe_os()`](crate::Frame::require_safe_os()) to control it.
+/// - `tostring` and `string.format` with `%p` on a table, function, thread or userdata expose its
+///   address.
+/// - `next` and `pairs` on a table that have a table, function, thread or userdata as a key
+///   iterate in the order of its address.
+/// - `math.randomseed` without arguments seeds from the current time and an address.
+/// - `collectgarbage` with `count` depends on the memory allocator.
+/// - All functions in IO library and `print`, `dofile`, `loadfile` and `require` access the host
+///   unless redirected.
+pub const NONDETERMINISTIC: &[&str] = &[
+    "os.time",
+    "os.getenv",
+    "os.execute",
+    "os.remove",
+    "os.rename",
+    "os.tmpname",
+    "os.setlocale",
+    "tostring",
+    "string.format",
+    "math.randomseed",
+    "next",
+    "pairs",
+    "collectgarbage",
+    "print",
+    "dofile",
+    "loadfile",
+    "require",
+    "io.*",
+];
+
+/// Source of time for `os.time`, `os.clock` and `os.date` in deterministic mode.
+///
+/// See [`LuaBuilder::deterministic()`](crate::LuaBuilder::deterministic()) for more details.
+pub trait Clock: 'static {
+    /// Returns number of seconds since Unix epoch.
+    fn time(&self) -> i64;
+
+    /// Returns an approximation of the amount in seconds of CPU time used by the program.
+    fn clock(&self) -> f64;
+}
+
+/// Options for deterministic mode.
+pub(crate) struct Deterministic {
+    pub seed: i64,
+    pub clock: Box<dyn Clock>,
+}
+
+/// Implementation of `os.clock`.
+pub(crate) fn clock(cx: &mut Context) -> Result<(), Error> {
+    let v = cx.extra1().clock.as_ref().unwrap().clock();
+
+    unsafe { zl_pushnumber(RawState::state(cx), v) };
+    unsafe { cx.release_values(1) };
+
+    Ok(())
+}
+
+/// Returns current time from [`Clock`].
+pub(crate) fn now(cx: &mut Context) -> Result<(), Error> {
+    let v = cx.extra1().clock.as_ref().unwrap().time();
+
+    unsafe { zl_pushinteger(RawState::state(cx), v) };
+    unsafe { cx.release_values(1) };
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChunkType, Frame, Library, Lua};
+
+    struct FixedClock;
+
+    impl Clock for FixedClock {
+        fn time(&self) -> i64 {
+            86400
+        }
+
+        fn clock(&self) -> f64 {
+            1.5
+        }
+    }
+
+    fn run(seed: i64) -> String {
+        let mut lua = Lua::builder()
+            .libs([Library::Base, Library::Math, Library::Os, Library::Table])
+            .deterministic(seed, FixedClock)
+            .build()
+            .unwrap();
+        let chunk = "assert(os.time() == 86400)\n\
+            assert(os.clock() == 1.5)\n\
+            assert(os.date('%Y-%m-%d %H') == '1970-01-02 00')\n\
+            assert(os.date('!%d', 0) == '01')\n\
+            local t = {}\n\
+            for i = 1, 64 do t['k' .. i] = true end\n\
+            local r = {math.random(1000000)}\n\
+            for k in pairs(t) do r[#r + 1] = k end\n\
+            error(table.concat(r, ','), 0)";
+        let f = lua.load(None, ChunkType::Text, chunk).unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("the chunk should raise an error"),
+            Err(e) => e,
+        };
+
+        e.to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn deterministic() {
+        assert_eq!(run(1), run(1));
+        assert_ne!(run(1), run(2));
+    }
+}
@@ -1,14 +1,17 @@
 pub use self::state::*;
 
 use crate::ffi::{
-    lua_State, zl_argerror, zl_checklstring, zl_error, zl_getfield, zl_getiuservalue,
-    zl_getmetatable, zl_isnil, zl_istable, zl_pop, zl_tolstring, zl_touserdata, zl_typeerror,
+    lua_Debug, lua_State, zl_argerror, zl_checklstring, zl_error, zl_errorlevel, zl_getfield,
+    zl_getinfo, zl_getiuservalue, zl_getmetatable, zl_getstack, zl_isnil, zl_istable, zl_pop,
+    zl_tolstring, zl_touserdata, zl_typeerror,
 };
 use crate::state::RawState;
 use crate::{
-    BorrowedTable, BorrowedUd, Error, ErrorKind, PositiveInt, TYPE_ID, UserType, Yield, is_boxed,
+    BorrowedTable, BorrowedUd, DebugInfo, Error, ErrorKind, Locals, PositiveInt, TAKEN, TYPE_ID,
+    UdRefMut, UserType, Yield, ud_borrow, ud_value,
 };
 use std::any::TypeId;
+use std::cell::Cell;
 use std::ffi::c_int;
 use std::marker::PhantomData;
 use std::num::NonZero;
@@ -22,6 +25,7 @@ pub struct Context<'a, S = NonYieldable> {
     state: S,
     args: c_int,
     ret: c_int,
+    borrows: Vec<&'a Cell<isize>>,
     phantom: PhantomData<&'a ()>,
 }
 
@@ -32,6 +36,7 @@ impl<'a, S: LocalState> Context<'a, S> {
             state,
             args,
             ret: 0,
+            borrows: Vec::new(),
             phantom: PhantomData,
         }
     }
@@ -142,50 +147,81 @@ impl<'a, S: LocalState> Context<'a, S> {
         Some(unsafe { BorrowedTable::new(self, n) })
     }
 
+    /// Get userdata argument or raise a Lua error if the argument is not `T`.
+    ///
+    /// The userdata will be borrowed until the current function returns. A Lua error will be raised
+    /// if it is currently borrowed by [`Self::to_ud_mut()`] or its value has been moved out with
+    /// [`OwnedUd::take()`](crate::OwnedUd::take()).
     pub fn to_ud<T: UserType>(&mut self, n: PositiveInt) -> BorrowedUd<'_, 'a, Self, T> {
-        if n > self.args {
-            // lua_touserdata require a valid index so we need to emulate luaL_checkudata behavior
-            // in this case.
-            self.arg_out_of_bound(n, T::name().to_bytes());
-        }
-
-        // We emulate luaL_checkudata here since we need to get additional field from metatable.
-        let ptr = unsafe { zl_touserdata(self.state.get(), n.get()).cast_const() };
+        let ud = self.check_ud::<T>(n);
+        let borrow = unsafe { ud_borrow(ud) };
 
-        if ptr.is_null() || unsafe { zl_getmetatable(self.state.get(), n.get()) == 0 } {
-            unsafe { zl_typeerror(self.state.get(), n.get(), T::name().as_ptr()) };
+        if borrow.get() < 0 {
+            self.borrow_error::<T>(n, borrow.get());
         }
 
-        unsafe { zl_getfield(self.state.get(), -1, TYPE_ID.as_ptr()) };
+        borrow.set(borrow.get() + 1);
+        self.borrows.push(borrow);
 
-        // SAFETY: TypeId is Copy.
-        let id = TypeId::of::<T>();
-        let ud = unsafe { zl_touserdata(self.state.get(), -1) };
-        let ok = unsafe { !ud.is_null() && ud.cast::<TypeId>().read_unaligned() == id };
-
-        unsafe { zl_pop(self.state.get(), 2) };
+        unsafe { BorrowedUd::new(self, n, &*ud_value::<T>(ud)) }
+    }
 
-        // Get pointer to UD.
-        let ud = if !ok {
-            unsafe { zl_typeerror(self.state.get(), n.get(), T::name().as_ptr()) };
-        } else if is_boxed::<T>() {
-            unsafe { (*ptr.cast::<Box<T>>()).as_ref() }
-        } else {
-            unsafe { &*ptr.cast::<T>() }
-        };
+    /// Get userdata argument as mutable or raise a Lua error if the argument is not `T`.
+    ///
+    /// A Lua error will be raised if the userdata is currently borrowed, which can happen when the
+    /// same value is passed twice or a method is re-entered while the value is still in use (e.g.
+    /// a method call back into Lua, which calls another method on the same value).
+    pub fn to_ud_mut<T: UserType>(&mut self, n: PositiveInt) -> UdRefMut<'a, T> {
+        let ud = self.check_ud::<T>(n);
+        let borrow = unsafe { ud_borrow(ud) };
+
+        if borrow.get() != 0 {
+            self.borrow_error::<T>(n, borrow.get());
+        }
 
-        unsafe { BorrowedUd::new(self, n, ud) }
+        unsafe { UdRefMut::new(borrow, &mut *ud_value::<T>(ud)) }
     }
 
     /// This method was designed to return a user value to Lua. Use [`Self::to_ud()`] then
     /// [`BorrowedUd::get_user_value()`] if you want to access the user value on Rust.
     #[inline(always)]
     pub fn push_uv<T: UserType>(&mut self, ud: PositiveInt, uv: NonZero<u16>) {
-        self.to_ud::<T>(ud);
+        self.check_ud::<T>(ud);
         unsafe { zl_getiuservalue(self.state.get(), ud.get(), uv.get()) };
         self.ret += 1;
     }
 
+    /// Returns information about a function in the call stack or [`None`] if `level` is greater
+    /// than the stack depth.
+    ///
+    /// Level 0 is the current running function (the Rust function that own this context) and
+    /// level 1 is the function that called the current function.
+    pub fn caller_info(&mut self, level: c_int) -> Option<DebugInfo> {
+        let mut ar = lua_Debug::default();
+
+        if unsafe { zl_getstack(self.state.get(), level, &mut ar) == 0 } {
+            return None;
+        }
+
+        unsafe { zl_getinfo(self.state.get(), c"nSl".as_ptr(), &mut ar) };
+
+        Some(unsafe { DebugInfo::new(&ar) })
+    }
+
+    /// Returns local variables of a function in the call stack or [`None`] if `level` is greater
+    /// than the stack depth.
+    ///
+    /// See [`Self::caller_info()`] for the meaning of `level`.
+    pub fn locals(&mut self, level: c_int) -> Option<Locals<Self>> {
+        let mut ar = lua_Debug::default();
+
+        if unsafe { zl_getstack(self.state.get(), level, &mut ar) == 0 } {
+            return None;
+        }
+
+        Some(unsafe { Locals::new(self, ar) })
+    }
+
     #[inline(always)]
     pub(crate) fn into_results(self) -> c_int {
         self.ret
@@ -200,6 +236,9 @@ impl<'a, S: LocalState> Context<'a, S> {
             },
             ErrorKind::ArgType(n, e) => (n, e),
             ErrorKind::Other(e) => unsafe { zl_error(self.state.get(), e.as_ptr().cast()) },
+            ErrorKind::WithLevel(l, e) => unsafe {
+                zl_errorlevel(self.state.get(), l, e.as_ptr().cast())
+            },
         };
 
         if n <= self.args {
@@ -211,6 +250,49 @@ impl<'a, S: LocalState> Context<'a, S> {
         }
     }
 
+    /// Returns a pointer to userdata block or raise a Lua error if the argument is not `T`.
+    fn check_ud<T: UserType>(&mut self, n: PositiveInt) -> *mut u8 {
+        if n > self.args {
+            // lua_touserdata require a valid index so we need to emulate luaL_checkudata behavior
+            // in this case.
+            self.arg_out_of_bound(n, T::name().to_bytes());
+        }
+
+        // We emulate luaL_checkudata here since we need to get additional field from metatable.
+        let ptr = unsafe { zl_touserdata(self.state.get(), n.get()) };
+
+        if ptr.is_null() || unsafe { zl_getmetatable(self.state.get(), n.get()) == 0 } {
+            unsafe { zl_typeerror(self.state.get(), n.get(), T::name().as_ptr()) };
+        }
+
+        unsafe { zl_getfield(self.state.get(), -1, TYPE_ID.as_ptr()) };
+
+        // SAFETY: TypeId is Copy.
+        let id = TypeId::of::<T>();
+        let ud = unsafe { zl_touserdata(self.state.get(), -1) };
+        let ok = unsafe { !ud.is_null() && ud.cast::<TypeId>().read_unaligned() == id };
+
+        unsafe { zl_pop(self.state.get(), 2) };
+
+        if !ok {
+            unsafe { zl_typeerror(self.state.get(), n.get(), T::name().as_ptr()) };
+        }
+
+        ptr
+    }
+
+    #[inline(never)]
+    fn borrow_error<T: UserType>(&mut self, n: PositiveInt, borrow: isize) -> ! {
+        let name = T::name().to_string_lossy();
+        let m = match borrow {
+            TAKEN => format!("{name} has been moved out"),
+            v if v < 0 => format!("{name} is already mutably borrowed"),
+            _ => format!("{name} is already borrowed"),
+        };
+
+        self.raise(Error::arg(n, m));
+    }
+
     #[inline(never)]
     fn arg_out_of_bound(&mut self, n: PositiveInt, expect: &[u8]) -> ! {
         let s = b" expected, got nil";
@@ -231,6 +313,15 @@ impl<'a> Context<'a, Yieldable> {
     }
 }
 
+impl<S> Drop for Context<'_, S> {
+    #[inline(always)]
+    fn drop(&mut self) {
+        for b in &self.borrows {
+            b.set(b.get() - 1);
+        }
+    }
+}
+
 impl<S: LocalState> RawState for Context<'_, S> {
     #[inline(always)]
     fn state(&mut self) -> *mut lua_State {
@@ -1,4 +1,6 @@
-use crate::{Frame, PositiveInt, UserType};
+use crate::ffi::{zl_pushinteger, zl_pushnumber};
+use crate::{Frame, PositiveInt, Ret, Type, UserType};
+use std::ffi::c_int;
 
 /// Type can be converted to Lua value.
 ///
@@ -24,6 +26,44 @@ unsafe impl IntoLua for bool {
     }
 }
 
+unsafe impl IntoLua for i32 {
+    const N: PositiveInt = PositiveInt::new(1).unwrap();
+
+    #[inline(always)]
+    fn into_lua<P: Frame>(self, p: &mut P) {
+        i64::from(self).into_lua(p);
+    }
+}
+
+unsafe impl IntoLua for i64 {
+    const N: PositiveInt = PositiveInt::new(1).unwrap();
+
+    #[inline(always)]
+    fn into_lua<P: Frame>(self, p: &mut P) {
+        unsafe { zl_pushinteger(p.state(), self) };
+        unsafe { p.release_values(1) };
+    }
+}
+
+unsafe impl IntoLua for f32 {
+    const N: PositiveInt = PositiveInt::new(1).unwrap();
+
+    #[inline(always)]
+    fn into_lua<P: Frame>(self, p: &mut P) {
+        f64::from(self).into_lua(p);
+    }
+}
+
+unsafe impl IntoLua for f64 {
+    const N: PositiveInt = PositiveInt::new(1).unwrap();
+
+    #[inline(always)]
+    fn into_lua<P: Frame>(self, p: &mut P) {
+        unsafe { zl_pushnumber(p.state(), self) };
+        unsafe { p.release_values(1) };
+    }
+}
+
 unsafe impl IntoLua for &str {
     const N: PositiveInt = PositiveInt::new(1).unwrap();
 
@@ -66,3 +106,111 @@ unsafe impl<T: IntoLua> IntoLua for Option<T> {
         }
     }
 }
+
+/// Type can be constructed from function results.
+///
+/// A value that is not present in [`Ret`] is treated as `nil`.
+pub trait FromLua: Sized {
+    /// Number of results this type consumes.
+    const N: c_int;
+
+    /// Returns [`None`] if the result at `n` (one-based) cannot be converted to this type.
+    fn from_lua<P: Frame>(r: &mut Ret<P>, n: c_int) -> Option<Self>;
+}
+
+impl FromLua for () {
+    const N: c_int = 0;
+
+    #[inline(always)]
+    fn from_lua<P: Frame>(_: &mut Ret<P>, _: c_int) -> Option<Self> {
+        Some(())
+    }
+}
+
+impl FromLua for bool {
+    const N: c_int = 1;
+
+    #[inline(always)]
+    fn from_lua<P: Frame>(r: &mut Ret<P>, n: c_int) -> Option<Self> {
+        if n > r.len() { None } else { r.to_bool(n) }
+    }
+}
+
+impl FromLua for i64 {
+    const N: c_int = 1;
+
+    #[inline(always)]
+    fn from_lua<P: Frame>(r: &mut Ret<P>, n: c_int) -> Option<Self> {
+        if n > r.len() { None } else { r.to_int(n) }
+    }
+}
+
+impl FromLua for f64 {
+    const N: c_int = 1;
+
+    #[inline(always)]
+    fn from_lua<P: Frame>(r: &mut Ret<P>, n: c_int) -> Option<Self> {
+        if n > r.len() { None } else { r.to_num(n) }
+    }
+}
+
+impl FromLua for Vec<u8> {
+    const N: c_int = 1;
+
+    #[inline(always)]
+    fn from_lua<P: Frame>(r: &mut Ret<P>, n: c_int) -> Option<Self> {
+        if n > r.len() {
+            None
+        } else {
+            r.to_bytes(n).map(|v| v.to_vec())
+        }
+    }
+}
+
+impl FromLua for String {
+    const N: c_int = 1;
+
+    #[inline(always)]
+    fn from_lua<P: Frame>(r: &mut Ret<P>, n: c_int) -> Option<Self> {
+        Vec::from_lua(r, n).and_then(|v| String::from_utf8(v).ok())
+    }
+}
+
+impl<T: FromLua> FromLua for Option<T> {
+    const N: c_int = T::N;
+
+    /// Returns `Some(None)` if the first value is `nil`.
+    #[inline(always)]
+    fn from_lua<P: Frame>(r: &mut Ret<P>, n: c_int) -> Option<Self> {
+        if n > r.len() || r.to_type(n) == Type::Nil {
+            Some(None)
+        } else {
+            T::from_lua(r, n).map(Some)
+        }
+    }
+}
+
+impl<A: FromLua, B: FromLua> FromLua for (A, B) {
+    const N: c_int = A::N + B::N;
+
+    #[inline(always)]
+    fn from_lua<P: Frame>(r: &mut Ret<P>, n: c_int) -> Option<Self> {
+        let a = A::from_lua(r, n)?;
+        let b = B::from_lua(r, n + A::N)?;
+
+        Some((a, b))
+    }
+}
+
+impl<A: FromLua, B: FromLua, C: FromLua> FromLua for (A, B, C) {
+    const N: c_int = A::N + B::N + C::N;
+
+    #[inline(always)]
+    fn from_lua<P: Frame>(r: &mut Ret<P>, n: c_int) -> Option<Self> {
+        let a = A::from_lua(r, n)?;
+        let b = B::from_lua(r, n + A::N)?;
+        let c = C::from_lua(r, n + A::N + B::N)?;
+
+        Some((a, b, c))
+    }
+}
@@ -0,0 +1,84 @@
+use crate::ffi::lua_Debug;
+use std::ffi::{CStr, CString, c_int};
+
+/// Information about a function or an active function in the call stack.
+///
+/// This is a copy of the information returned from `lua_getinfo`.
+pub struct DebugInfo {
+    source: Box<[u8]>,
+    short_src: CString,
+    what: CString,
+    current_line: Option<c_int>,
+    line_defined: Option<c_int>,
+    name: Option<CString>,
+    name_what: CString,
+}
+
+impl DebugInfo {
+    /// # Safety
+    /// `ar` must be filled by `lua_getinfo` with `S`. The fields that was not filled must be the
+    /// same as [`lua_Debug::default()`].
+    pub(crate) unsafe fn new(ar: &lua_Debug) -> Self {
+        let source = unsafe { std::slice::from_raw_parts(ar.source.cast(), ar.srclen) };
+        let name = match ar.name.is_null() {
+            true => None,
+            false => Some(unsafe { CStr::from_ptr(ar.name).to_owned() }),
+        };
+        let name_what = match ar.namewhat.is_null() {
+            true => CString::default(),
+            false => unsafe { CStr::from_ptr(ar.namewhat).to_owned() },
+        };
+
+        Self {
+            source: source.into(),
+            short_src: unsafe { CStr::from_ptr(ar.short_src.as_ptr()).to_owned() },
+            what: unsafe { CStr::from_ptr(ar.what).to_owned() },
+            current_line: Some(ar.currentline).filter(|&v| v > 0),
+            line_defined: Some(ar.linedefined).filter(|&v| v > 0),
+            name,
+            name_what,
+        }
+    }
+
+    /// Returns the source of the chunk that created the function.
+    ///
+    /// This is the chunk name that was passed to [`Frame::load()`](crate::Frame::load()) or the
+    /// chunk itself if no name was specified.
+    pub fn source(&self) -> &[u8] {
+        &self.source
+    }
+
+    /// Returns a "printable" version of [`Self::source()`] to be used in error messages.
+    pub fn short_src(&self) -> &CStr {
+        &self.short_src
+    }
+
+    /// Returns `Lua` if the function is a Lua function, `C` if it is a Rust function and `main` if
+    /// it is the main part of a chunk.
+    pub fn what(&self) -> &CStr {
+        &self.what
+    }
+
+    /// Returns the current line of the function or [`None`] if no line information is available
+    /// (e.g. the function is not a Lua function or not active).
+    pub fn current_line(&self) -> Option<c_int> {
+        self.current_line
+    }
+
+    /// Returns the line where the definition of the function starts or [`None`] if the function
+    /// is not a Lua function.
+    pub fn line_defined(&self) -> Option<c_int> {
+        self.line_defined
+    }
+
+    /// Returns a reasonable name for the function or [`None`] if Lua cannot find one.
+    pub fn name(&self) -> Option<&CStr> {
+        self.name.as_deref()
+    }
+
+    /// Returns how the name from [`Self::name()`] was found (e.g. `global`, `local`, `method`,
+    /// `field`, `upvalue`). Returns an empty string if no name was found.
+    pub fn name_what(&self) -> &CStr {
+        &self.name_what
+    }
+}
@@ -0,0 +1,53 @@
+use crate::ffi::{lua_Debug, lua_State, zl_getlocal, zl_pop, zl_type};
+use crate::state::RawState;
+use crate::{Frame, PositiveInt, Value};
+use std::ffi::{CStr, CString, c_int};
+
+/// Provides methods to get local variables of an active function.
+///
+/// All values returned from this struct will be removed from the stack when dropped.
+pub struct Locals<'a, P: Frame> {
+    parent: &'a mut P,
+    ar: lua_Debug,
+}
+
+impl<'a, P: Frame> Locals<'a, P> {
+    /// # Safety
+    /// `ar` must be filled by `lua_getstack` and the function must be active while this struct is
+    /// alive.
+    #[inline(always)]
+    pub(crate) unsafe fn new(parent: &'a mut P, ar: lua_Debug) -> Self {
+        Self { parent, ar }
+    }
+
+    /// Returns the name and the value of local variable `n` or [`None`] if `n` is greater than the
+    /// number of active local variables.
+    ///
+    /// To iterate all local variables start `n` from one until this method returns [`None`].
+    /// Variable names starting with `(` represent variables with no known names (e.g.
+    /// `(temporary)`, `(vararg)` and `(C temporary)`).
+    pub fn get(&mut self, n: PositiveInt) -> Option<(CString, Value<Self>)> {
+        let name = unsafe { zl_getlocal(self.parent.state(), &self.ar, n.get()) };
+
+        if name.is_null() {
+            return None;
+        }
+
+        let name = unsafe { CStr::from_ptr(name).to_owned() };
+        let ty = unsafe { zl_type(self.parent.state(), -1) };
+
+        Some((name, unsafe { Value::from_top(self, ty) }))
+    }
+}
+
+impl<P: Frame> RawState for Locals<'_, P> {
+    #[inline(always)]
+    fn state(&mut self) -> *mut lua_State {
+        self.parent.state()
+    }
+
+    #[inline(always)]
+    unsafe fn release_values(&mut self, n: c_int) {
+        unsafe { zl_pop(self.state(), n) };
+    }
+}
@@ -0,0 +1,96 @@
+pub use self::info::*;
+pub use self::local::*;
+pub use self::upvalue::*;
+
+mod info;
+mod local;
+mod upvalue;
+
+#[cfg(test)]
+mod tests {
+    use crate::ffi::zl_pushlightuserdata;
+    use crate::state::RawState;
+    use crate::{ChunkType, Frame, Lua, PositiveInt, Type, Value};
+
+    #[test]
+    fn caller() {
+        let mut lua = Lua::new(None).unwrap();
+
+        lua.set_global(c"check").push_fn(|cx| {
+            let info = cx.caller_info(1).unwrap();
+
+            assert_eq!(info.source(), b"=test");
+            assert_eq!(info.current_line(), Some(2));
+
+            // Check local.
+            let mut l = cx.locals(1).unwrap();
+            let (n, v) = l.get(PositiveInt::ONE).unwrap();
+
+            assert_eq!(n.as_bytes(), b"x");
+            match v {
+                Value::Number(mut v) => assert_eq!(v.to_int(), Some(5)),
+                _ => panic!("unexpected value"),
+            }
+
+            Ok(())
+        });
+
+        let f = lua
+            .load(Some(c"=test"), ChunkType::Text, "local x = 5\ncheck()")
+            .unwrap();
+
+        assert!(f.call().is_ok());
+    }
+
+    #[test]
+    fn upvalue() {
+        let mut lua = Lua::new(None).unwrap();
+        let mut f = lua
+            .load(Some(c"=test"), ChunkType::Text, "return x")
+            .unwrap();
+
+        assert_eq!(f.info().what(), c"main");
+
+        let mut u = f.upvalues();
+        let (n, v) = u.get(PositiveInt::ONE).unwrap();
+
+        assert_eq!(n.as_bytes(), b"_ENV");
+        assert!(matches!(v, Value::Table(_)));
+    }
+
+    #[test]
+    fn opaque() {
+        let mut lua = Lua::new(None).unwrap();
+
+        lua.require_coroutine(true);
+        lua.set_global(c"check").push_fn(|cx| {
+            let mut l = cx.locals(1).unwrap();
+            let (n, v) = l.get(PositiveInt::ONE).unwrap();
+
+            assert_eq!(n.as_bytes(), b"co");
+            assert!(matches!(v, Value::Thread(_)));
+
+            Ok(())
+        });
+
+        let f = lua
+            .load(
+                Some(c"=test"),
+                ChunkType::Text,
+                "local co = coroutine.create(function() end)\ncheck()",
+            )
+            .unwrap();
+
+        assert!(f.call().is_ok());
+
+        // Light userdata.
+        let mut v = 0u8;
+
+        unsafe { zl_pushlightuserdata(lua.state(), (&raw mut v).cast()) };
+
+        match unsafe { Value::from_top(&mut lua, Type::LightUserData) } {
+            Value::LightUserData(mut p) => assert_eq!(p.to_ptr(), &raw mut v),
+            _ => panic!("unexpected value"),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use crate::ffi::{lua_State, zl_getupvalue, zl_pop, zl_type};
+use crate::state::RawState;
+use crate::{Frame, PositiveInt, Value};
+use std::ffi::{CStr, CString, c_int};
+
+/// Provides methods to get upvalues of a function.
+///
+/// All values returned from this struct will be removed from the stack when dropped.
+pub struct Upvalues<'a, P: Frame> {
+    parent: &'a mut P,
+    func: c_int,
+}
+
+impl<'a, P: Frame> Upvalues<'a, P> {
+    /// # Safety
+    /// `func` must be an absolute index of a function.
+    #[inline(always)]
+    pub(crate) unsafe fn new(parent: &'a mut P, func: c_int) -> Self {
+        Self { parent, func }
+    }
+
+    /// Returns the name and the value of upvalue `n` or [`None`] if `n` is greater than the number
+    /// of upvalues.
+    ///
+    /// To iterate all upvalues start `n` from one until this method returns [`None`]. The name is
+    /// empty for upvalues of a Rust function or a stripped Lua function.
+    pub fn get(&mut self, n: PositiveInt) -> Option<(CString, Value<Self>)> {
+        let name = unsafe { zl_getupvalue(self.parent.state(), self.func, n.get()) };
+
+        if name.is_null() {
+            return None;
+        }
+
+        let name = unsafe { CStr::from_ptr(name).to_owned() };
+        let ty = unsafe { zl_type(self.parent.state(), -1) };
+
+        Some((name, unsafe { Value::from_top(self, ty) }))
+    }
+}
+
+impl<P: Frame> RawState for Upvalues<'_, P> {
+    #[inline(always)]
+    fn state(&mut self) -> *mut lua_State {
+        self.parent.state()
+    }
+
+    #[inline(always)]
+    unsafe fn release_values(&mut self, n: c_int) {
+        unsafe { zl_pop(self.state(), n) };
+    }
+}
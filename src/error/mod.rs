@@ -2,6 +2,7 @@ pub use self::msg::*;
 
 use crate::{Frame, PositiveInt, TableKey, Value};
 use std::borrow::Cow;
+use std::ffi::c_int;
 
 mod msg;
 
@@ -94,10 +95,25 @@ impl Error {
 
     /// `msg` are typically concise lowercase sentences without trailing punctuation (e.g. `failed
     /// to open 'foo'`).
+    ///
+    /// The message will be prefixed with the location of the function that called the Rust
+    /// function (e.g. `chunk:3:`) if it is a Lua function. This is the same as
+    /// `Error::with_level(1, msg)`.
     pub fn other(msg: impl Into<ErrorMsg>) -> Self {
         Self(ErrorKind::Other(msg.into().into()))
     }
 
+    /// Create an error with the message prefixed by the location of the function at `level` in the
+    /// call stack, which is the same as `level` argument of Lua `error` function.
+    ///
+    /// Level 1 is the function that called the Rust function, level 2 is the function that called
+    /// the function at level 1 and so on. No location will be added if `level` is zero or the
+    /// function at `level` is not a Lua function. This is useful when the Rust function was not
+    /// called directly from a script (e.g. called via `pcall`).
+    pub fn with_level(level: c_int, msg: impl Into<ErrorMsg>) -> Self {
+        Self(ErrorKind::WithLevel(level, msg.into().into()))
+    }
+
     /// `msg` are typically concise lowercase sentences without trailing punctuation (e.g. `failed
     /// to open 'foo'`).
     pub fn with_source(msg: impl Into<String>, src: impl std::error::Error) -> Self {
@@ -142,6 +158,9 @@ pub(crate) enum ErrorKind {
     /// # Safety
     /// The value must null-terminated.
     Other(Cow<'static, [u8]>),
+    /// # Safety
+    /// Second value must null-terminated.
+    WithLevel(c_int, Cow<'static, [u8]>),
 }
 
 impl From<Error> for ErrorKind {
@@ -149,3 +168,47 @@ impl From<Error> for ErrorKind {
         value.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChunkType, Lua};
+
+    #[test]
+    fn location() {
+        let mut lua = Lua::new(None).unwrap();
+
+        lua.require_base();
+        lua.set_global(c"located")
+            .push_fn(|_| Err(Error::other(c"foo")));
+        lua.set_global(c"indirect")
+            .push_fn(|_| Err(Error::with_level(2, c"bar")));
+        lua.set_global(c"plain")
+            .push_fn(|_| Err(Error::with_level(0, c"baz")));
+
+        // The caller of located() is pcall, which is not a Lua function.
+        let chunk = "local _, a = pcall(located)\n\
+            local _, b = pcall(indirect)\n\
+            local _, c = pcall(plain)\n\
+            error(a .. ',' .. b .. ',' .. c, 0)";
+        let f = lua.load(Some(c"=test"), ChunkType::Text, chunk).unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("unexpected success"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "foo,test:2: bar,baz");
+        drop(e);
+
+        // Direct call from Lua.
+        let f = lua
+            .load(Some(c"=test"), ChunkType::Text, "located()")
+            .unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("unexpected success"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "test:1: foo");
+    }
+}
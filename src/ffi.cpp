@@ -2,37 +2,109 @@
 #include <lualib.h>
 #include <lauxlib.h>
 
+#include <string>
 #include <type_traits>
 
+#include <errno.h>
 #include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
 #include <string.h>
 
 static_assert(sizeof(lua_Integer) == sizeof(int64_t));
 static_assert(std::is_signed<lua_Integer>::value);
+static_assert(std::is_same<lua_Number, double>::value);
 static_assert(sizeof(lua_KContext) == sizeof(intptr_t));
 static_assert(LUA_EXTRASPACE == sizeof(void *) * 2);
 static_assert(LUA_MINSTACK == 20);
 static_assert(LUA_MULTRET == -1);
+static_assert(LUA_ERRMEM == 4);
+static_assert(LUA_IDSIZE == 60);
+static_assert(LUA_GCSTEP == 5);
+static_assert(LUA_GCGEN == 10);
+static_assert(LUA_GCINC == 11);
 
 extern "C" {
     int ZL_REGISTRYINDEX = LUA_REGISTRYINDEX;
     const char *ZL_LOADED_TABLE = LUA_LOADED_TABLE;
+    const char *ZL_PRELOAD_TABLE = LUA_PRELOAD_TABLE;
 }
 
-extern "C" lua_State *zl_newstate()
+// Provided by zl-sys. See zlconf.h for more details.
+extern "C" lua_State *zlsys_newstate(lua_Alloc f, void *ud, unsigned int seed);
+
+struct Allocator {
+    size_t used;
+    size_t limit;
+};
+
+static void *alloc(void *ud, void *ptr, size_t osize, size_t nsize)
+{
+    auto a = static_cast<Allocator *>(ud);
+
+    // osize is a type of object when ptr is null.
+    if (!ptr) {
+        osize = 0;
+    }
+
+    if (nsize == 0) {
+        free(ptr);
+        a->used -= osize;
+        return nullptr;
+    }
+
+    // Check limit.
+    if (nsize > osize && nsize - osize > a->limit - a->used) {
+        return nullptr;
+    }
+
+    ptr = realloc(ptr, nsize);
+
+    if (ptr) {
+        a->used = a->used - osize + nsize;
+    }
+
+    return ptr;
+}
+
+extern "C" lua_State *zl_newstate(size_t limit, unsigned int seed)
 {
-    auto L = luaL_newstate();
+    auto a = new Allocator;
 
-    if (L) {
-        memset(lua_getextraspace(L), 0, LUA_EXTRASPACE);
+    a->used = 0;
+    a->limit = limit;
+
+    // Create lua_State.
+    auto L = zlsys_newstate(alloc, a, seed);
+
+    if (!L) {
+        delete a;
+        return nullptr;
     }
 
+    memset(lua_getextraspace(L), 0, LUA_EXTRASPACE);
+
     return L;
 }
 
 extern "C" void zl_close(lua_State *L)
 {
+    void *ud;
+
+    lua_getallocf(L, &ud);
     lua_close(L);
+
+    delete static_cast<Allocator *>(ud);
+}
+
+extern "C" void zl_setwarnf(lua_State *L, lua_WarnFunction f, void *ud)
+{
+    lua_setwarnf(L, f, ud);
+}
+
+extern "C" int zl_gc(lua_State *L, int what, int a, int b, int c)
+{
+    return lua_gc(L, what, a, b, c);
 }
 
 extern "C" lua_CFunction zl_atpanic(lua_State *L, int (*panicf) (lua_State *L))
@@ -45,6 +117,11 @@ extern "C" void zl_require_base(lua_State *L)
     luaL_requiref(L, LUA_GNAME, luaopen_base, 0);
 }
 
+extern "C" void zl_require_debug(lua_State *L, bool global)
+{
+    luaL_requiref(L, LUA_DBLIBNAME, luaopen_debug, global);
+}
+
 extern "C" void zl_require_coroutine(lua_State *L, bool global)
 {
     luaL_requiref(L, LUA_COLIBNAME, luaopen_coroutine, global);
@@ -55,14 +132,146 @@ extern "C" void zl_require_io(lua_State *L, bool global)
     luaL_requiref(L, LUA_IOLIBNAME, luaopen_io, global);
 }
 
-extern "C" void zl_require_math(lua_State *L, bool global)
+static bool is_loaded(lua_State *L, const char *name)
 {
+    luaL_getsubtable(L, LUA_REGISTRYINDEX, LUA_LOADED_TABLE);
+    lua_getfield(L, -1, name);
+
+    auto r = lua_toboolean(L, -1);
+
+    lua_pop(L, 2);
+
+    return r;
+}
+
+extern "C" void zl_require_math(lua_State *L, bool global, const int64_t *seed)
+{
+    auto loaded = is_loaded(L, LUA_MATHLIBNAME);
+
     luaL_requiref(L, LUA_MATHLIBNAME, luaopen_math, global);
+
+    if (loaded || !seed) {
+        return;
+    }
+
+    // Replace the random seed.
+    lua_getfield(L, -1, "randomseed");
+    lua_pushinteger(L, static_cast<lua_Integer>(*seed));
+    lua_call(L, 1, 0);
 }
 
-extern "C" void zl_require_os(lua_State *L, bool global)
+extern "C" bool zl_require_os(lua_State *L, bool global)
 {
+    auto loaded = is_loaded(L, LUA_OSLIBNAME);
+
     luaL_requiref(L, LUA_OSLIBNAME, luaopen_os, global);
+
+    return !loaded;
+}
+
+static int det_time(lua_State *L)
+{
+    // Use the original function if a table is specified.
+    if (lua_isnoneornil(L, 1)) {
+        lua_settop(L, 0);
+        lua_pushvalue(L, lua_upvalueindex(2));
+    } else {
+        lua_settop(L, 1);
+        lua_pushvalue(L, lua_upvalueindex(1));
+        lua_insert(L, 1);
+    }
+
+    lua_call(L, lua_gettop(L) - 1, 1);
+
+    return 1;
+}
+
+static int det_date(lua_State *L)
+{
+    auto f = luaL_optstring(L, 1, "%c");
+
+    // Get time.
+    if (lua_isnoneornil(L, 2)) {
+        lua_settop(L, 1);
+        lua_pushvalue(L, lua_upvalueindex(2));
+        lua_call(L, 0, 1);
+    } else {
+        lua_settop(L, 2);
+    }
+
+    // Always use UTC since local time zone depends on the machine.
+    if (*f == '!') {
+        lua_pushstring(L, f);
+    } else {
+        lua_pushfstring(L, "!%s", f);
+    }
+
+    lua_replace(L, 1);
+
+    // Invoke the original function.
+    lua_pushvalue(L, lua_upvalueindex(1));
+    lua_insert(L, 1);
+    lua_call(L, 2, 1);
+
+    return 1;
+}
+
+extern "C" void zl_setclock(lua_State *L)
+{
+    // Stack: os table, current time function.
+    lua_getfield(L, -2, "time");
+    lua_pushvalue(L, -2);
+    lua_pushcclosure(L, det_time, 2);
+    lua_setfield(L, -3, "time");
+
+    lua_getfield(L, -2, "date");
+    lua_insert(L, -2);
+    lua_pushcclosure(L, det_date, 2);
+    lua_setfield(L, -2, "date");
+}
+
+extern "C" void zl_require_package(lua_State *L, bool global, bool native)
+{
+    luaL_requiref(L, LUA_LOADLIBNAME, luaopen_package, global);
+
+    if (native) {
+        return;
+    }
+
+    // Disable C loaders.
+    lua_pushnil(L);
+    lua_setfield(L, -2, "loadlib");
+    lua_pushliteral(L, "");
+    lua_setfield(L, -2, "cpath");
+
+    if (lua_getfield(L, -1, "searchers") == LUA_TTABLE) {
+        lua_pushnil(L);
+        lua_seti(L, -2, 4);
+        lua_pushnil(L);
+        lua_seti(L, -2, 3);
+    }
+
+    lua_pop(L, 1);
+}
+
+extern "C" bool zl_getsearchers(lua_State *L)
+{
+    luaL_getsubtable(L, LUA_REGISTRYINDEX, LUA_LOADED_TABLE);
+
+    if (lua_getfield(L, -1, LUA_LOADLIBNAME) != LUA_TTABLE) {
+        lua_pop(L, 2);
+        return false;
+    }
+
+    if (lua_getfield(L, -1, "searchers") != LUA_TTABLE) {
+        lua_pop(L, 3);
+        return false;
+    }
+
+    lua_replace(L, -3);
+    lua_pop(L, 1);
+
+    return true;
 }
 
 extern "C" void zl_require_string(lua_State *L, bool global)
@@ -80,11 +289,511 @@ extern "C" void zl_require_utf8(lua_State *L, bool global)
     luaL_requiref(L, LUA_UTF8LIBNAME, luaopen_utf8, global);
 }
 
+static void set_stdfile(lua_State *L, int which)
+{
+    static const char *const names[] = { "stdin", "stdout", "stderr" };
+
+    // The io table must be below the file.
+    if (which == 0) {
+        lua_pushvalue(L, -1);
+        lua_setfield(L, LUA_REGISTRYINDEX, "_IO_input");
+    } else if (which == 1) {
+        lua_pushvalue(L, -1);
+        lua_setfield(L, LUA_REGISTRYINDEX, "_IO_output");
+    }
+
+    lua_setfield(L, -2, names[which]);
+}
+
+struct zl_file_vtable {
+    intptr_t (*read) (void *ud, char *buf, size_t size);
+    intptr_t (*write) (void *ud, const char *buf, size_t size);
+    int (*seek) (void *ud, int64_t *offset, int whence);
+    int (*close) (void *ud);
+};
+
+struct FileCookie {
+    void *ud;
+    const zl_file_vtable *vt;
+};
+
+static intptr_t file_result(intptr_t r)
+{
+    if (r < 0) {
+        errno = static_cast<int>(-r);
+        return -1;
+    }
+
+    return r;
+}
+
+#if defined(__unix__) || defined(__APPLE__)
+#if defined(__APPLE__) || defined(__FreeBSD__) || defined(__NetBSD__) || defined(__OpenBSD__)
+static int cookie_read(void *c, char *buf, int size)
+{
+    auto f = static_cast<FileCookie *>(c);
+    return static_cast<int>(file_result(f->vt->read(f->ud, buf, static_cast<size_t>(size))));
+}
+
+static int cookie_write(void *c, const char *buf, int size)
+{
+    auto f = static_cast<FileCookie *>(c);
+    return static_cast<int>(file_result(f->vt->write(f->ud, buf, static_cast<size_t>(size))));
+}
+
+static fpos_t cookie_seek(void *c, fpos_t offset, int whence)
+{
+    auto f = static_cast<FileCookie *>(c);
+    int64_t off = offset;
+    auto r = f->vt->seek(f->ud, &off, whence);
+
+    if (r < 0) {
+        errno = -r;
+        return -1;
+    }
+
+    return off;
+}
+#else
+static ssize_t cookie_read(void *c, char *buf, size_t size)
+{
+    auto f = static_cast<FileCookie *>(c);
+    return file_result(f->vt->read(f->ud, buf, size));
+}
+
+static ssize_t cookie_write(void *c, const char *buf, size_t size)
+{
+    auto f = static_cast<FileCookie *>(c);
+    auto r = file_result(f->vt->write(f->ud, buf, size));
+
+    // fopencookie requires 0 instead of -1 on error.
+    return r < 0 ? 0 : r;
+}
+
+static int cookie_seek(void *c, off64_t *offset, int whence)
+{
+    auto f = static_cast<FileCookie *>(c);
+    int64_t off = *offset;
+    auto r = f->vt->seek(f->ud, &off, whence);
+
+    if (r < 0) {
+        errno = -r;
+        return -1;
+    }
+
+    *offset = off;
+
+    return 0;
+}
+#endif
+
+static int cookie_close(void *c)
+{
+    auto f = static_cast<FileCookie *>(c);
+    auto r = f->vt->close(f->ud);
+
+    delete f;
+
+    if (r < 0) {
+        errno = -r;
+        return -1;
+    }
+
+    return 0;
+}
+
+static int file_close(lua_State *L)
+{
+    auto p = static_cast<luaL_Stream *>(luaL_checkudata(L, 1, LUA_FILEHANDLE));
+    auto r = fclose(p->f);
+
+    return luaL_fileresult(L, r == 0, nullptr);
+}
+
+static luaL_Stream *new_file(lua_State *L, void *ud, const zl_file_vtable *vt, const char *mode)
+{
+    // The metatable must be on the top of stack. Create the userdata first so we don't leak FILE when
+    // memory error. We own ud until FILE has been created so we need to close it on error.
+    luaL_Stream *p;
+    FileCookie *c;
+
+    try {
+        p = static_cast<luaL_Stream *>(lua_newuserdatauv(L, sizeof(luaL_Stream), 0));
+        c = new FileCookie;
+    } catch (...) {
+        vt->close(ud);
+        throw;
+    }
+
+    p->f = nullptr;
+    p->closef = nullptr;
+
+    lua_insert(L, -2);
+    lua_setmetatable(L, -2);
+
+    // Create FILE.
+    c->ud = ud;
+    c->vt = vt;
+
+#if defined(__APPLE__) || defined(__FreeBSD__) || defined(__NetBSD__) || defined(__OpenBSD__)
+    p->f = funopen(c, cookie_read, cookie_write, cookie_seek, cookie_close);
+#else
+    cookie_io_functions_t io;
+
+    io.read = cookie_read;
+    io.write = cookie_write;
+    io.seek = cookie_seek;
+    io.close = cookie_close;
+
+    p->f = fopencookie(c, mode, io);
+#endif
+
+    if (!p->f) {
+        delete c;
+        vt->close(ud);
+        luaL_error(L, "cannot create FILE: %s", strerror(errno));
+    }
+
+    p->closef = file_close;
+
+    return p;
+}
+
+extern "C" bool zl_pushfile(lua_State *L, void *ud, const zl_file_vtable *vt)
+{
+    if (luaL_getmetatable(L, LUA_FILEHANDLE) != LUA_TTABLE) {
+        lua_pop(L, 1);
+        return false;
+    }
+
+    new_file(L, ud, vt, "r+");
+
+    return true;
+}
+
+extern "C" bool zl_setstdio(lua_State *L, int which, void *ud, const zl_file_vtable *vt)
+{
+    static const char *const modes[] = { "r", "w", "w" };
+
+    // Get io library.
+    luaL_getsubtable(L, LUA_REGISTRYINDEX, LUA_LOADED_TABLE);
+
+    if (lua_getfield(L, -1, LUA_IOLIBNAME) != LUA_TTABLE) {
+        lua_pop(L, 2);
+        return false;
+    }
+
+    lua_remove(L, -2);
+
+    if (luaL_getmetatable(L, LUA_FILEHANDLE) != LUA_TTABLE) {
+        lua_pop(L, 2);
+        return false;
+    }
+
+    // Create FILE. We don't buffer standard streams so the output appear immediately.
+    auto p = new_file(L, ud, vt, modes[which]);
+
+    setvbuf(p->f, nullptr, _IONBF, 0);
+
+    set_stdfile(L, which);
+    lua_pop(L, 1);
+
+    return true;
+}
+#endif
+
+extern "C" bool zl_setstdfile(lua_State *L, int which)
+{
+    // The file must be on the top.
+    luaL_getsubtable(L, LUA_REGISTRYINDEX, LUA_LOADED_TABLE);
+
+    if (lua_getfield(L, -1, LUA_IOLIBNAME) != LUA_TTABLE) {
+        lua_pop(L, 3);
+        return false;
+    }
+
+    lua_remove(L, -2);
+    lua_insert(L, -2);
+
+    set_stdfile(L, which);
+    lua_pop(L, 1);
+
+    return true;
+}
+
+static void check_file(lua_State *L, int arg)
+{
+    // Use io.type since the file can be either a standard file or a Rust file.
+    lua_getfield(L, lua_upvalueindex(1), "type");
+    lua_pushvalue(L, arg);
+    lua_call(L, 1, 1);
+
+    auto t = lua_tostring(L, -1);
+
+    if (!t) {
+        luaL_typeerror(L, arg, LUA_FILEHANDLE);
+    } else if (strcmp(t, "closed file") == 0) {
+        luaL_error(L, "attempt to use a closed file");
+    }
+
+    lua_pop(L, 1);
+}
+
+static int call_method(lua_State *L, const char *name)
+{
+    // The file must be the first argument.
+    auto n = lua_gettop(L);
+
+    lua_getfield(L, 1, name);
+    lua_insert(L, 1);
+    lua_call(L, n, LUA_MULTRET);
+
+    return lua_gettop(L);
+}
+
+static int route_default(lua_State *L)
+{
+    lua_getfield(L, LUA_REGISTRYINDEX, lua_tostring(L, lua_upvalueindex(2)));
+    lua_insert(L, 1);
+
+    return call_method(L, lua_tostring(L, lua_upvalueindex(3)));
+}
+
+static int route_close(lua_State *L)
+{
+    if (lua_isnone(L, 1)) {
+        lua_getfield(L, LUA_REGISTRYINDEX, "_IO_output");
+    }
+
+    check_file(L, 1);
+    lua_settop(L, 1);
+
+    return call_method(L, "close");
+}
+
+static int route_stdfile(lua_State *L)
+{
+    auto key = lua_tostring(L, lua_upvalueindex(2));
+
+    if (!lua_isnoneornil(L, 1)) {
+        if (lua_type(L, 1) == LUA_TSTRING) {
+            auto name = lua_tostring(L, 1);
+
+            lua_getfield(L, lua_upvalueindex(1), "open");
+            lua_pushvalue(L, 1);
+            lua_pushvalue(L, lua_upvalueindex(3));
+            lua_call(L, 2, 2);
+
+            if (lua_isnil(L, -2)) {
+                return luaL_error(L, "cannot open file '%s' (%s)", name, lua_tostring(L, -1));
+            }
+
+            lua_pop(L, 1);
+        } else {
+            check_file(L, 1);
+            lua_pushvalue(L, 1);
+        }
+
+        lua_setfield(L, LUA_REGISTRYINDEX, key);
+    }
+
+    lua_getfield(L, LUA_REGISTRYINDEX, key);
+
+    return 1;
+}
+
+static int route_deflines(lua_State *L)
+{
+    if (lua_isnone(L, 1)) {
+        lua_pushnil(L);
+    }
+
+    // Invoke the original function if a file name is specified.
+    if (!lua_isnil(L, 1)) {
+        lua_pushvalue(L, lua_upvalueindex(2));
+        lua_insert(L, 1);
+        lua_call(L, lua_gettop(L) - 1, LUA_MULTRET);
+
+        return lua_gettop(L);
+    }
+
+    lua_getfield(L, LUA_REGISTRYINDEX, "_IO_input");
+    lua_replace(L, 1);
+    check_file(L, 1);
+
+    return call_method(L, "lines");
+}
+
+static int route_type(lua_State *L)
+{
+    luaL_checkany(L, 1);
+
+    auto ud = luaL_testudata(L, 1, lua_tostring(L, lua_upvalueindex(2)));
+
+    if (!ud) {
+        lua_pushvalue(L, lua_upvalueindex(1));
+        lua_insert(L, 1);
+        lua_call(L, lua_gettop(L) - 1, 1);
+
+        return 1;
+    }
+
+    auto closed = reinterpret_cast<bool (*) (void *)>(lua_touserdata(L, lua_upvalueindex(3)));
+
+    lua_pushstring(L, closed(ud) ? "closed file" : "file");
+
+    return 1;
+}
+
+extern "C" bool zl_routestdio(lua_State *L, const char *name, bool (*closed) (void *ud))
+{
+    static const char *const defaults[][3] = {
+        { "read", "_IO_input", "read" },
+        { "write", "_IO_output", "write" },
+        { "flush", "_IO_output", "flush" },
+    };
+    static const char *const files[][3] = {
+        { "input", "_IO_input", "r" },
+        { "output", "_IO_output", "w" },
+    };
+
+    // Get io library.
+    luaL_getsubtable(L, LUA_REGISTRYINDEX, LUA_LOADED_TABLE);
+
+    if (lua_getfield(L, -1, LUA_IOLIBNAME) != LUA_TTABLE) {
+        lua_pop(L, 2);
+        return false;
+    }
+
+    lua_remove(L, -2);
+
+    // Check if already routed.
+    lua_getfield(L, -1, "write");
+
+    if (lua_tocfunction(L, -1) == route_default) {
+        lua_pop(L, 2);
+        return true;
+    }
+
+    lua_pop(L, 1);
+
+    // The stock functions read the default files from the registry as a FILE, which is not the case
+    // for a Rust file so we need to invoke the methods on the default files instead.
+    for (auto &d : defaults) {
+        lua_pushvalue(L, -1);
+        lua_pushstring(L, d[1]);
+        lua_pushstring(L, d[2]);
+        lua_pushcclosure(L, route_default, 3);
+        lua_setfield(L, -2, d[0]);
+    }
+
+    for (auto &f : files) {
+        lua_pushvalue(L, -1);
+        lua_pushstring(L, f[1]);
+        lua_pushstring(L, f[2]);
+        lua_pushcclosure(L, route_stdfile, 3);
+        lua_setfield(L, -2, f[0]);
+    }
+
+    lua_pushvalue(L, -1);
+    lua_pushcclosure(L, route_close, 1);
+    lua_setfield(L, -2, "close");
+
+    lua_pushvalue(L, -1);
+    lua_getfield(L, -2, "lines");
+    lua_pushcclosure(L, route_deflines, 2);
+    lua_setfield(L, -2, "lines");
+
+    lua_getfield(L, -1, "type");
+    lua_pushstring(L, name);
+    lua_pushlightuserdata(L, reinterpret_cast<void *>(closed));
+    lua_pushcclosure(L, route_type, 3);
+    lua_setfield(L, -2, "type");
+
+    lua_pop(L, 1);
+
+    return true;
+}
+
+static int file_line(lua_State *L)
+{
+    auto n = static_cast<int>(lua_tointeger(L, lua_upvalueindex(1)));
+
+    // Same as io.lines, which raise an error if the read is failed.
+    lua_settop(L, 0);
+    luaL_checkstack(L, n + 2, "too many arguments");
+    lua_pushvalue(L, lua_upvalueindex(2));
+    lua_getfield(L, -1, "read");
+    lua_insert(L, -2);
+
+    for (int i = 1; i <= n; i++) {
+        lua_pushvalue(L, lua_upvalueindex(2 + i));
+    }
+
+    lua_call(L, n + 1, LUA_MULTRET);
+
+    auto r = lua_gettop(L);
+
+    if (lua_toboolean(L, 1)) {
+        return r;
+    } else if (r > 1) {
+        return luaL_error(L, "%s", lua_tostring(L, 2));
+    }
+
+    return 0;
+}
+
+extern "C" void zl_filelines(lua_State *L)
+{
+    // The file must be the first value follow by the formats.
+    auto n = lua_gettop(L) - 1;
+
+    luaL_argcheck(L, n <= 250, 252, "too many arguments");
+    lua_pushinteger(L, n);
+    lua_insert(L, 1);
+    lua_pushcclosure(L, file_line, n + 2);
+}
+
+extern "C" int64_t zl_checkinteger(lua_State *L, int arg)
+{
+    return luaL_checkinteger(L, arg);
+}
+
+extern "C" size_t zl_stringtonumber(lua_State *L, const char *s)
+{
+    return lua_stringtonumber(L, s);
+}
+
+extern "C" const char *zl_tolstringmeta(lua_State *L, int index, size_t *len)
+{
+    return luaL_tolstring(L, index, len);
+}
+
 extern "C" bool zl_load(lua_State *L, const char *name, const char *chunk, size_t len, const char *mode)
 {
     return luaL_loadbufferx(L, chunk, len, name, mode) == LUA_OK;
 }
 
+extern "C" int zl_loadbuffer(lua_State *L, const char *name, const char *chunk, size_t len, const char *mode)
+{
+    return luaL_loadbufferx(L, chunk, len, name, mode);
+}
+
+extern "C" int zl_version()
+{
+    return LUA_VERSION_RELEASE_NUM;
+}
+
+extern "C" bool zl_loadx(lua_State *L, lua_Reader reader, void *data, const char *name, const char *mode)
+{
+    return lua_load(L, reader, data, name, mode) == LUA_OK;
+}
+
+extern "C" int zl_dump(lua_State *L, lua_Writer writer, void *data, bool strip)
+{
+    return lua_dump(L, writer, data, strip);
+}
+
 extern "C" bool zl_pcall(lua_State *L, int nargs, int nresults, int msgh)
 {
     return lua_pcall(L, nargs, nresults, msgh) == LUA_OK;
@@ -100,6 +809,16 @@ extern "C" void zl_pushnil(lua_State *L)
     lua_pushnil(L);
 }
 
+extern "C" void zl_pushinteger(lua_State *L, int64_t n)
+{
+    lua_pushinteger(L, static_cast<lua_Integer>(n));
+}
+
+extern "C" void zl_pushnumber(lua_State *L, double n)
+{
+    lua_pushnumber(L, static_cast<lua_Number>(n));
+}
+
 extern "C" void zl_pushboolean(lua_State *L, bool b)
 {
     lua_pushboolean(L, b);
@@ -155,11 +874,26 @@ extern "C" bool zl_istable(lua_State *L, int index)
     return lua_istable(L, index) != 0;
 }
 
+extern "C" bool zl_toboolean(lua_State *L, int index)
+{
+    return lua_toboolean(L, index);
+}
+
 extern "C" int64_t zl_tointegerx(lua_State *L, int index, int *isnum)
 {
     return static_cast<int64_t>(lua_tointegerx(L, index, isnum));
 }
 
+extern "C" double zl_tonumberx(lua_State *L, int index, int *isnum)
+{
+    return static_cast<double>(lua_tonumberx(L, index, isnum));
+}
+
+extern "C" bool zl_isinteger(lua_State *L, int index)
+{
+    return lua_isinteger(L, index) != 0;
+}
+
 extern "C" const char *zl_tolstring(lua_State *L, int index, size_t *len)
 {
     return lua_tolstring(L, index, len);
@@ -215,6 +949,16 @@ extern "C" void zl_setfield(lua_State *L, int index, const char *k)
     lua_setfield(L, index, k);
 }
 
+extern "C" int zl_rawgetp(lua_State *L, int index, const void *p)
+{
+    return lua_rawgetp(L, index, p);
+}
+
+extern "C" void zl_rawsetp(lua_State *L, int index, const void *p)
+{
+    lua_rawsetp(L, index, p);
+}
+
 extern "C" int zl_getsubtable(lua_State *L, int idx, const char *fname)
 {
     return luaL_getsubtable(L, idx, fname);
@@ -270,6 +1014,153 @@ extern "C" void zl_setglobal(lua_State *L, const char *name)
     lua_setglobal(L, name);
 }
 
+extern "C" int64_t zl_rawlen(lua_State *L, int index)
+{
+    return static_cast<int64_t>(lua_rawlen(L, index));
+}
+
+extern "C" void zl_rotate(lua_State *L, int idx, int n)
+{
+    lua_rotate(L, idx, n);
+}
+
+extern "C" const char *zl_setupvalue(lua_State *L, int funcindex, int n)
+{
+    return lua_setupvalue(L, funcindex, n);
+}
+
+extern "C" bool zl_modulefree(lua_State *L, int loaded, const char *name)
+{
+    // The module itself must not be loaded and its parents must be either a table or not loaded.
+    loaded = lua_absindex(L, loaded);
+
+    for (auto dot = strchr(name, '.'); dot; dot = strchr(dot + 1, '.')) {
+        std::string path(name, dot - name);
+        auto t = lua_getfield(L, loaded, path.c_str());
+
+        lua_pop(L, 1);
+
+        if (t != LUA_TNIL && t != LUA_TTABLE) {
+            return false;
+        }
+    }
+
+    auto t = lua_getfield(L, loaded, name);
+
+    lua_pop(L, 1);
+
+    return t == LUA_TNIL;
+}
+
+extern "C" void zl_setmodule(lua_State *L, int loaded, const char *name, bool global)
+{
+    auto dot = strchr(name, '.');
+    auto seg = name;
+    std::string path;
+
+    // The module must be on the top.
+    lua_pushvalue(L, -1);
+    lua_setfield(L, loaded, name);
+
+    if (!dot) {
+        if (global) {
+            lua_setglobal(L, name);
+        } else {
+            lua_pop(L, 1);
+        }
+
+        return;
+    }
+
+    while (dot) {
+        std::string key(seg, dot - seg);
+
+        path.assign(name, dot - name);
+
+        // Get or create the table for this level.
+        if (lua_getfield(L, loaded, path.c_str()) != LUA_TTABLE) {
+            lua_pop(L, 1);
+            lua_newtable(L);
+            lua_pushvalue(L, -1);
+            lua_setfield(L, loaded, path.c_str());
+
+            if (seg != name) {
+                lua_pushvalue(L, -1);
+                lua_setfield(L, -3, key.c_str());
+            }
+        }
+
+        if (seg == name) {
+            if (global) {
+                lua_pushvalue(L, -1);
+                lua_setglobal(L, key.c_str());
+            }
+        } else {
+            lua_replace(L, -2);
+        }
+
+        seg = dot + 1;
+        dot = strchr(seg, '.');
+    }
+
+    // Put the module to its parent.
+    lua_insert(L, -2);
+    lua_setfield(L, -2, seg);
+    lua_pop(L, 1);
+}
+
+extern "C" void zl_sandbox(lua_State *L, const char *name)
+{
+    // Get library name.
+    auto dot = strchr(name, '.');
+
+    lua_pushglobaltable(L);
+
+    if (!dot) {
+        // Copy the whole value. We need to do a shallow copy on table so the sandbox cannot
+        // modify the original one.
+        if (lua_getfield(L, -1, name) == LUA_TTABLE) {
+            lua_newtable(L);
+            lua_pushnil(L);
+
+            while (lua_next(L, -3)) {
+                lua_pushvalue(L, -2);
+                lua_insert(L, -2);
+                lua_settable(L, -4);
+            }
+
+            lua_remove(L, -2);
+        }
+
+        lua_setfield(L, -3, name);
+        lua_pop(L, 1);
+        return;
+    }
+
+    // Get library.
+    std::string lib(name, dot - name);
+    auto field = dot + 1;
+
+    if (lua_getfield(L, -1, lib.c_str()) != LUA_TTABLE) {
+        lua_pop(L, 2);
+        return;
+    }
+
+    lua_getfield(L, -1, field);
+
+    // Get library in the sandbox.
+    if (lua_getfield(L, -4, lib.c_str()) != LUA_TTABLE) {
+        lua_pop(L, 1);
+        lua_newtable(L);
+        lua_pushvalue(L, -1);
+        lua_setfield(L, -6, lib.c_str());
+    }
+
+    lua_insert(L, -2);
+    lua_setfield(L, -2, field);
+    lua_pop(L, 3);
+}
+
 extern "C" void zl_replace(lua_State *L, int index)
 {
     lua_replace(L, index);
@@ -285,6 +1176,70 @@ extern "C" int zl_error(lua_State *L, const char *msg)
     return luaL_error(L, "%s", msg);
 }
 
+extern "C" int zl_errorlevel(lua_State *L, int level, const char *msg)
+{
+    if (level > 0) {
+        luaL_where(L, level);
+        lua_pushstring(L, msg);
+        lua_concat(L, 2);
+    } else {
+        lua_pushstring(L, msg);
+    }
+
+    return lua_error(L);
+}
+
+extern "C" int zl_raise(lua_State *L)
+{
+    return lua_error(L);
+}
+
+extern "C" void zl_sethook(lua_State *L, lua_Hook f, int mask, int count)
+{
+    lua_sethook(L, f, mask, count);
+}
+
+extern "C" void zl_inherithook(lua_State *L, lua_State *from)
+{
+    auto f = lua_gethook(from);
+    auto mask = lua_gethookmask(from);
+    auto count = lua_gethookcount(from);
+
+    if (lua_gethook(L) != f || lua_gethookmask(L) != mask || lua_gethookcount(L) != count) {
+        lua_sethook(L, f, mask, count);
+    }
+}
+
+extern "C" int zl_getinfo(lua_State *L, const char *what, lua_Debug *ar)
+{
+    return lua_getinfo(L, what, ar);
+}
+
+extern "C" int zl_getstack(lua_State *L, int level, lua_Debug *ar)
+{
+    return lua_getstack(L, level, ar);
+}
+
+extern "C" const char *zl_getlocal(lua_State *L, const lua_Debug *ar, int n)
+{
+    return lua_getlocal(L, ar, n);
+}
+
+extern "C" const char *zl_getupvalue(lua_State *L, int funcindex, int n)
+{
+    return lua_getupvalue(L, funcindex, n);
+}
+
+extern "C" bool zl_isyieldable(lua_State *L)
+{
+    return lua_isyieldable(L) != 0;
+}
+
+extern "C" int zl_yield(lua_State *L, int nresults)
+{
+    return lua_yield(L, nresults);
+}
+
 extern "C" void *zl_getextraspace(lua_State *L)
 {
     return lua_getextraspace(L);
@@ -304,3 +1259,227 @@ extern "C" int zl_yieldk(lua_State *L, int nresults, intptr_t ctx, int (*k) (lua
 {
     return lua_yieldk(L, nresults, ctx, k);
 }
+
+static int route_iofile(lua_State *L)
+{
+    // Open the file with the replaced io.open.
+    if (lua_type(L, 1) == LUA_TSTRING) {
+        auto name = lua_tostring(L, 1);
+
+        lua_pushvalue(L, lua_upvalueindex(2));
+        lua_pushvalue(L, 1);
+        lua_pushvalue(L, lua_upvalueindex(3));
+        lua_call(L, 2, 2);
+
+        if (lua_isnil(L, -2)) {
+            return luaL_error(L, "cannot open file '%s' (%s)", name, lua_tostring(L, -1));
+        }
+
+        lua_pop(L, 1);
+        lua_replace(L, 1);
+    }
+
+    // Invoke the original function.
+    lua_pushvalue(L, lua_upvalueindex(1));
+    lua_insert(L, 1);
+    lua_call(L, lua_gettop(L) - 1, LUA_MULTRET);
+
+    return lua_gettop(L);
+}
+
+static int route_lines(lua_State *L)
+{
+    if (lua_isnone(L, 1)) {
+        lua_pushnil(L);
+    }
+
+    // Use the original function for the default input.
+    if (lua_isnil(L, 1)) {
+        lua_pushvalue(L, lua_upvalueindex(1));
+        lua_insert(L, 1);
+        lua_call(L, lua_gettop(L) - 1, LUA_MULTRET);
+
+        return lua_gettop(L);
+    }
+
+    // Open the file with the replaced io.open.
+    luaL_checkstring(L, 1);
+
+    lua_pushvalue(L, lua_upvalueindex(2));
+    lua_pushvalue(L, 1);
+    lua_pushliteral(L, "r");
+    lua_call(L, 2, 2);
+
+    if (lua_isnil(L, -2)) {
+        return luaL_error(L, "%s", lua_tostring(L, -1));
+    }
+
+    lua_pop(L, 1);
+    lua_replace(L, 1);
+
+    // Same as io.lines, which returns the file as a to-be-closed variable.
+    auto n = lua_gettop(L);
+
+    lua_getfield(L, 1, "lines");
+    lua_insert(L, 1);
+    lua_pushvalue(L, 2);
+    lua_insert(L, 1);
+    lua_call(L, n, 1);
+    lua_pushnil(L);
+    lua_pushnil(L);
+    lua_pushvalue(L, 1);
+
+    return 4;
+}
+
+extern "C" void zl_routeio(lua_State *L)
+{
+    static const char *const names[] = { "input", "output" };
+    static const char *const modes[] = { "r", "w" };
+
+    // The io table with the replaced open must be on the top.
+    for (int i = 0; i < 2; i++) {
+        lua_getfield(L, -1, names[i]);
+        lua_getfield(L, -2, "open");
+        lua_pushstring(L, modes[i]);
+        lua_pushcclosure(L, route_iofile, 3);
+        lua_setfield(L, -2, names[i]);
+    }
+
+    lua_getfield(L, -1, "lines");
+    lua_getfield(L, -2, "open");
+    lua_pushcclosure(L, route_lines, 2);
+    lua_setfield(L, -2, "lines");
+}
+
+static int dofile_cont(lua_State *L, int, lua_KContext)
+{
+    return lua_gettop(L) - 1;
+}
+
+extern "C" int zl_dofile(lua_State *L)
+{
+    // The function to call must be the only value after the file name.
+    lua_callk(L, 0, LUA_MULTRET, 0, dofile_cont);
+
+    return dofile_cont(L, 0, 0);
+}
+
+static const char *chunk_mode(lua_State *L, int arg)
+{
+    // Force text mode if binary chunk is not allowed. Zero arg mean no mode argument.
+    auto binary = static_cast<const bool *>(lua_touserdata(L, lua_upvalueindex(1)));
+
+    if (!*binary) {
+        return "t";
+    }
+
+    return arg ? luaL_optstring(L, arg, "bt") : "bt";
+}
+
+static int load_result(lua_State *L, int status, int env)
+{
+    if (status != LUA_OK) {
+        luaL_pushfail(L);
+        lua_insert(L, -2);
+        return 2;
+    }
+
+    if (env) {
+        lua_pushvalue(L, env);
+
+        if (!lua_setupvalue(L, -2, 1)) {
+            lua_pop(L, 1);
+        }
+    }
+
+    return 1;
+}
+
+static const char *load_reader(lua_State *L, void *, size_t *size)
+{
+    luaL_checkstack(L, 2, "too many nested functions");
+    lua_pushvalue(L, 1);
+    lua_call(L, 0, 1);
+
+    if (lua_isnil(L, -1)) {
+        lua_pop(L, 1);
+        *size = 0;
+        return nullptr;
+    } else if (!lua_isstring(L, -1)) {
+        luaL_error(L, "reader function must return a string");
+    }
+
+    // Keep the string in the slot above all arguments so it will not be collected while parsing.
+    lua_replace(L, 5);
+
+    return lua_tolstring(L, 5, size);
+}
+
+static int base_load(lua_State *L)
+{
+    size_t len;
+    auto s = lua_tolstring(L, 1, &len);
+    auto mode = chunk_mode(L, 3);
+    auto env = !lua_isnone(L, 4) ? 4 : 0;
+    int status;
+
+    if (s) {
+        auto name = luaL_optstring(L, 2, s);
+        status = luaL_loadbufferx(L, s, len, name, mode);
+    } else {
+        auto name = luaL_optstring(L, 2, "=(load)");
+        luaL_checktype(L, 1, LUA_TFUNCTION);
+        lua_settop(L, 5);
+        status = lua_load(L, load_reader, nullptr, name, mode);
+    }
+
+    return load_result(L, status, env);
+}
+
+static int base_loadfile(lua_State *L)
+{
+    auto name = luaL_optstring(L, 1, nullptr);
+    auto mode = chunk_mode(L, 2);
+    auto env = !lua_isnone(L, 3) ? 3 : 0;
+    auto status = luaL_loadfilex(L, name, mode);
+
+    return load_result(L, status, env);
+}
+
+static int base_dofile(lua_State *L)
+{
+    auto name = luaL_optstring(L, 1, nullptr);
+
+    lua_settop(L, 1);
+
+    if (luaL_loadfilex(L, name, chunk_mode(L, 0)) != LUA_OK) {
+        return lua_error(L);
+    }
+
+    lua_callk(L, 0, LUA_MULTRET, 0, dofile_cont);
+
+    return dofile_cont(L, 0, 0);
+}
+
+extern "C" void zl_textonly(lua_State *L, const bool *binary)
+{
+    static const luaL_Reg funcs[] = {
+        { "load", base_load },
+        { "loadfile", base_loadfile },
+        { "dofile", base_dofile },
+        { nullptr, nullptr }
+    };
+
+    // The base library must be on the top. Skip if it was already replaced.
+    lua_getfield(L, -1, "load");
+    auto done = lua_tocfunction(L, -1) == base_load;
+    lua_pop(L, 1);
+
+    if (done) {
+        return;
+    }
+
+    lua_pushlightuserdata(L, const_cast<bool *>(binary));
+    luaL_setfuncs(L, funcs, 1);
+}
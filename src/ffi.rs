@@ -1,30 +1,174 @@
 use crate::Type;
-use std::ffi::{c_char, c_int, c_void};
+use std::ffi::{c_char, c_int, c_uint, c_void};
+use std::ptr::{null, null_mut};
 
 pub const LUA_OK: c_int = 0;
 pub const LUA_YIELD: c_int = 1;
+pub const LUA_ERRMEM: c_int = 4;
 
 pub const LUA_MULTRET: c_int = -1;
 
+pub const LUA_HOOKCALL: c_int = 0;
+pub const LUA_HOOKRET: c_int = 1;
+pub const LUA_HOOKLINE: c_int = 2;
+pub const LUA_HOOKCOUNT: c_int = 3;
+pub const LUA_HOOKTAILCALL: c_int = 4;
+
+pub const LUA_MASKCALL: c_int = 1 << LUA_HOOKCALL;
+pub const LUA_MASKRET: c_int = 1 << LUA_HOOKRET;
+pub const LUA_MASKLINE: c_int = 1 << LUA_HOOKLINE;
+pub const LUA_MASKCOUNT: c_int = 1 << LUA_HOOKCOUNT;
+
+pub const LUA_IDSIZE: usize = 60;
+
+pub const LUA_GCSTOP: c_int = 0;
+pub const LUA_GCRESTART: c_int = 1;
+pub const LUA_GCCOLLECT: c_int = 2;
+pub const LUA_GCCOUNT: c_int = 3;
+pub const LUA_GCCOUNTB: c_int = 4;
+pub const LUA_GCSTEP: c_int = 5;
+pub const LUA_GCISRUNNING: c_int = 9;
+pub const LUA_GCGEN: c_int = 10;
+pub const LUA_GCINC: c_int = 11;
+
 #[allow(non_camel_case_types)]
 #[repr(C)]
 pub struct lua_State([u8; 0]);
 
+#[allow(non_camel_case_types)]
+#[repr(C)]
+pub struct lua_Debug {
+    pub event: c_int,
+    pub name: *const c_char,
+    pub namewhat: *const c_char,
+    pub what: *const c_char,
+    pub source: *const c_char,
+    pub srclen: usize,
+    pub currentline: c_int,
+    pub linedefined: c_int,
+    pub lastlinedefined: c_int,
+    pub nups: u8,
+    pub nparams: u8,
+    pub isvararg: c_char,
+    pub istailcall: c_char,
+    pub ftransfer: u16,
+    pub ntransfer: u16,
+    pub short_src: [c_char; LUA_IDSIZE],
+    i_ci: *mut c_void,
+}
+
+impl Default for lua_Debug {
+    fn default() -> Self {
+        Self {
+            event: 0,
+            name: null(),
+            namewhat: null(),
+            what: null(),
+            source: null(),
+            srclen: 0,
+            currentline: 0,
+            linedefined: 0,
+            lastlinedefined: 0,
+            nups: 0,
+            nparams: 0,
+            isvararg: 0,
+            istailcall: 0,
+            ftransfer: 0,
+            ntransfer: 0,
+            short_src: [0; LUA_IDSIZE],
+            i_ci: null_mut(),
+        }
+    }
+}
+
+#[allow(non_camel_case_types)]
+pub type lua_Hook = unsafe extern "C-unwind" fn(*mut lua_State, *mut lua_Debug);
+
+#[cfg(unix)]
+#[allow(non_camel_case_types)]
+#[repr(C)]
+pub struct zl_file_vtable {
+    pub read: unsafe extern "C" fn(ud: *mut c_void, buf: *mut c_char, size: usize) -> isize,
+    pub write: unsafe extern "C" fn(ud: *mut c_void, buf: *const c_char, size: usize) -> isize,
+    pub seek: unsafe extern "C" fn(ud: *mut c_void, offset: *mut i64, whence: c_int) -> c_int,
+    pub close: unsafe extern "C" fn(ud: *mut c_void) -> c_int,
+}
+
+#[allow(non_camel_case_types)]
+pub type lua_Reader =
+    unsafe extern "C-unwind" fn(*mut lua_State, *mut c_void, *mut usize) -> *const c_char;
+
+#[allow(non_camel_case_types)]
+pub type lua_Writer =
+    unsafe extern "C-unwind" fn(*mut lua_State, *const c_void, usize, *mut c_void) -> c_int;
+
+#[allow(non_camel_case_types)]
+pub type lua_WarnFunction =
+    unsafe extern "C-unwind" fn(ud: *mut c_void, msg: *const c_char, tocont: c_int);
+
 unsafe extern "C-unwind" {
     pub static ZL_REGISTRYINDEX: c_int;
     pub static ZL_LOADED_TABLE: *const c_char;
+    pub static ZL_PRELOAD_TABLE: *const c_char;
 
-    pub safe fn zl_newstate() -> *mut lua_State;
+    pub safe fn zl_version() -> c_int;
+    pub safe fn zl_newstate(limit: usize, seed: c_uint) -> *mut lua_State;
     pub fn zl_close(L: *mut lua_State);
+    pub fn zl_setwarnf(L: *mut lua_State, f: Option<lua_WarnFunction>, ud: *mut c_void);
+    pub fn zl_gc(L: *mut lua_State, what: c_int, a: c_int, b: c_int, c: c_int) -> c_int;
     pub fn zl_atpanic(L: *mut lua_State, panicf: Option<extern "C" fn(*mut lua_State) -> c_int>);
     pub fn zl_require_base(L: *mut lua_State);
+    pub fn zl_textonly(L: *mut lua_State, binary: *const bool);
     pub fn zl_require_coroutine(L: *mut lua_State, global: bool);
+    pub fn zl_require_debug(L: *mut lua_State, global: bool);
     pub fn zl_require_io(L: *mut lua_State, global: bool);
-    pub fn zl_require_math(L: *mut lua_State, global: bool);
-    pub fn zl_require_os(L: *mut lua_State, global: bool);
+    pub fn zl_require_math(L: *mut lua_State, global: bool, seed: *const i64);
+    pub fn zl_require_os(L: *mut lua_State, global: bool) -> bool;
+    pub fn zl_setclock(L: *mut lua_State);
+    pub fn zl_require_package(L: *mut lua_State, global: bool, native: bool);
+    pub fn zl_getsearchers(L: *mut lua_State) -> bool;
     pub fn zl_require_string(L: *mut lua_State, global: bool);
     pub fn zl_require_table(L: *mut lua_State, global: bool);
     pub fn zl_require_utf8(L: *mut lua_State, global: bool);
+    #[cfg(unix)]
+    pub fn zl_pushfile(L: *mut lua_State, ud: *mut c_void, vt: *const zl_file_vtable) -> bool;
+    #[cfg(unix)]
+    pub fn zl_setstdio(
+        L: *mut lua_State,
+        which: c_int,
+        ud: *mut c_void,
+        vt: *const zl_file_vtable,
+    ) -> bool;
+    #[cfg(any(not(unix), test))]
+    pub fn zl_setstdfile(L: *mut lua_State, which: c_int) -> bool;
+    #[cfg(any(not(unix), test))]
+    pub fn zl_routestdio(
+        L: *mut lua_State,
+        name: *const c_char,
+        closed: unsafe extern "C" fn(ud: *mut c_void) -> bool,
+    ) -> bool;
+    #[cfg(any(not(unix), test))]
+    pub fn zl_filelines(L: *mut lua_State);
+    #[cfg(any(not(unix), test))]
+    pub fn zl_checkinteger(L: *mut lua_State, arg: c_int) -> i64;
+    #[cfg(any(not(unix), test))]
+    pub fn zl_stringtonumber(L: *mut lua_State, s: *const c_char) -> usize;
+    pub fn zl_tolstringmeta(L: *mut lua_State, index: c_int, len: *mut usize) -> *const c_char;
+    pub fn zl_loadbuffer(
+        L: *mut lua_State,
+        name: *const c_char,
+        chunk: *const c_char,
+        len: usize,
+        mode: *const c_char,
+    ) -> c_int;
+    pub fn zl_loadx(
+        L: *mut lua_State,
+        reader: lua_Reader,
+        data: *mut c_void,
+        name: *const c_char,
+        mode: *const c_char,
+    ) -> bool;
+    pub fn zl_dump(L: *mut lua_State, writer: lua_Writer, data: *mut c_void, strip: bool) -> c_int;
     pub fn zl_load(
         L: *mut lua_State,
         name: *const c_char,
@@ -35,6 +179,8 @@ unsafe extern "C-unwind" {
     pub fn zl_pcall(L: *mut lua_State, nargs: c_int, nresults: c_int, msgh: c_int) -> bool;
     pub fn zl_checkstack(L: *mut lua_State, n: c_int);
     pub fn zl_pushnil(L: *mut lua_State);
+    pub fn zl_pushinteger(L: *mut lua_State, n: i64);
+    pub fn zl_pushnumber(L: *mut lua_State, n: f64);
     pub fn zl_pushboolean(L: *mut lua_State, b: bool);
     pub fn zl_pushlstring(L: *mut lua_State, s: *const c_char, len: usize) -> *const c_char;
     pub fn zl_pushlightuserdata(L: *mut lua_State, p: *mut c_void);
@@ -50,7 +196,10 @@ unsafe extern "C-unwind" {
     pub fn zl_argerror(L: *mut lua_State, arg: c_int, extramsg: *const c_char) -> !;
     pub fn zl_isnil(L: *mut lua_State, index: c_int) -> bool;
     pub fn zl_istable(L: *mut lua_State, index: c_int) -> bool;
+    pub fn zl_toboolean(L: *mut lua_State, index: c_int) -> bool;
     pub fn zl_tointegerx(L: *mut lua_State, index: c_int, isnum: *mut c_int) -> i64;
+    pub fn zl_tonumberx(L: *mut lua_State, index: c_int, isnum: *mut c_int) -> f64;
+    pub fn zl_isinteger(L: *mut lua_State, index: c_int) -> bool;
     pub fn zl_tolstring(L: *mut lua_State, index: c_int, len: *mut usize) -> *const c_char;
     pub fn zl_touserdata(L: *mut lua_State, index: c_int) -> *mut u8;
     pub fn zl_type(L: *mut lua_State, index: c_int) -> Type;
@@ -62,6 +211,8 @@ unsafe extern "C-unwind" {
     pub fn zl_seti(L: *mut lua_State, index: c_int, n: i64);
     pub fn zl_getfield(L: *mut lua_State, index: c_int, k: *const c_char) -> Type;
     pub fn zl_setfield(L: *mut lua_State, index: c_int, k: *const c_char);
+    pub fn zl_rawgetp(L: *mut lua_State, index: c_int, p: *const c_void) -> Type;
+    pub fn zl_rawsetp(L: *mut lua_State, index: c_int, p: *const c_void);
     pub fn zl_getsubtable(L: *mut lua_State, idx: c_int, fname: *const c_char) -> c_int;
     pub fn zl_newuserdatauv(L: *mut lua_State, size: usize, nuvalue: c_int) -> *mut u8;
     pub fn zl_setiuservalue(L: *mut lua_State, index: c_int, n: u16) -> c_int;
@@ -73,9 +224,27 @@ unsafe extern "C-unwind" {
     pub fn zl_getmetafield(L: *mut lua_State, obj: c_int, e: *const c_char) -> Type;
     pub fn zl_upvalueindex(i: c_int) -> c_int;
     pub fn zl_setglobal(L: *mut lua_State, name: *const c_char);
+    pub fn zl_rawlen(L: *mut lua_State, index: c_int) -> i64;
+    pub fn zl_rotate(L: *mut lua_State, idx: c_int, n: c_int);
+    pub fn zl_setupvalue(L: *mut lua_State, funcindex: c_int, n: c_int) -> *const c_char;
+    pub fn zl_modulefree(L: *mut lua_State, loaded: c_int, name: *const c_char) -> bool;
+    pub fn zl_setmodule(L: *mut lua_State, loaded: c_int, name: *const c_char, global: bool);
+    pub fn zl_sandbox(L: *mut lua_State, name: *const c_char);
+    pub fn zl_routeio(L: *mut lua_State);
+    pub fn zl_dofile(L: *mut lua_State) -> c_int;
     pub fn zl_replace(L: *mut lua_State, index: c_int);
     pub fn zl_pop(L: *mut lua_State, n: c_int);
     pub fn zl_error(L: *mut lua_State, msg: *const c_char) -> !;
+    pub fn zl_errorlevel(L: *mut lua_State, level: c_int, msg: *const c_char) -> !;
+    pub fn zl_raise(L: *mut lua_State) -> !;
+    pub fn zl_sethook(L: *mut lua_State, f: Option<lua_Hook>, mask: c_int, count: c_int);
+    pub fn zl_inherithook(L: *mut lua_State, from: *mut lua_State);
+    pub fn zl_getinfo(L: *mut lua_State, what: *const c_char, ar: *mut lua_Debug) -> c_int;
+    pub fn zl_getstack(L: *mut lua_State, level: c_int, ar: *mut lua_Debug) -> c_int;
+    pub fn zl_getlocal(L: *mut lua_State, ar: *const lua_Debug, n: c_int) -> *const c_char;
+    pub fn zl_getupvalue(L: *mut lua_State, funcindex: c_int, n: c_int) -> *const c_char;
+    pub fn zl_isyieldable(L: *mut lua_State) -> bool;
+    pub fn zl_yield(L: *mut lua_State, nresults: c_int) -> c_int;
     pub fn zl_getextraspace(L: *mut lua_State) -> *mut *mut ();
     pub fn zl_newthread(L: *mut lua_State) -> *mut lua_State;
     pub fn zl_resume(
@@ -88,6 +257,6 @@ unsafe extern "C-unwind" {
         L: *mut lua_State,
         nresults: c_int,
         ctx: isize,
-        k: unsafe extern "C-unwind" fn(L: *mut lua_State, status: c_int, ctx: isize) -> c_int,
+        k: unsafe extern "C-unwind" fn(*mut lua_State, c_int, isize) -> c_int,
     ) -> !;
 }
@@ -0,0 +1,111 @@
+use super::FileIo;
+use crate::ffi::zl_file_vtable;
+use std::ffi::{c_char, c_int, c_void};
+use std::io::{ErrorKind, SeekFrom};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+const EIO: isize = 5;
+const EBADF: isize = 9;
+const EINVAL: isize = 22;
+const ESPIPE: isize = 29;
+
+pub(crate) static VTABLE: zl_file_vtable = zl_file_vtable {
+    read,
+    write,
+    seek,
+    close,
+};
+
+/// Returns a pointer to pass to [`VTABLE`].
+pub(crate) fn into_cookie(f: impl FileIo + 'static) -> *mut c_void {
+    let f: Box<dyn FileIo> = Box::new(f);
+
+    Box::into_raw(Box::new(f)).cast()
+}
+
+/// Converts `e` to `errno`. `unsupported` will be used for [`ErrorKind::Unsupported`] that does not
+/// come from the OS.
+fn errno(e: std::io::Error, unsupported: isize) -> isize {
+    match e.raw_os_error() {
+        Some(v) => v as isize,
+        None if e.kind() == ErrorKind::Unsupported => unsupported,
+        None => EIO,
+    }
+}
+
+// The following functions are called by C runtime so we can't unwind through it. A panic will be
+// reported as EIO instead.
+
+unsafe extern "C" fn read(ud: *mut c_void, buf: *mut c_char, size: usize) -> isize {
+    let f = unsafe { &mut *ud.cast::<Box<dyn FileIo>>() };
+    let buf = unsafe { std::slice::from_raw_parts_mut(buf.cast(), size) };
+
+    match catch_unwind(AssertUnwindSafe(|| f.read(buf))) {
+        Ok(Ok(v)) => v.try_into().unwrap_or(isize::MAX),
+        Ok(Err(e)) => -errno(e, EBADF),
+        Err(_) => -EIO,
+    }
+}
+
+unsafe extern "C" fn write(ud: *mut c_void, buf: *const c_char, size: usize) -> isize {
+    let f = unsafe { &mut *ud.cast::<Box<dyn FileIo>>() };
+    let buf = unsafe { std::slice::from_raw_parts(buf.cast(), size) };
+
+    // C runtime never write more than isize::MAX.
+    match catch_unwind(AssertUnwindSafe(|| f.write(buf))) {
+        Ok(Ok(_)) => size as isize,
+        Ok(Err(e)) => -errno(e, EBADF),
+        Err(_) => -EIO,
+    }
+}
+
+unsafe extern "C" fn seek(ud: *mut c_void, offset: *mut i64, whence: c_int) -> c_int {
+    let f = unsafe { &mut *ud.cast::<Box<dyn FileIo>>() };
+    let off = unsafe { offset.read() };
+    let pos = match whence {
+        0 => match u64::try_from(off) {
+            Ok(v) => SeekFrom::Start(v),
+            Err(_) => return -EINVAL as c_int,
+        },
+        1 => SeekFrom::Current(off),
+        2 => SeekFrom::End(off),
+        _ => return -EINVAL as c_int,
+    };
+
+    let e = match catch_unwind(AssertUnwindSafe(|| f.seek(pos))) {
+        Ok(Ok(v)) => match i64::try_from(v) {
+            Ok(v) => {
+                unsafe { offset.write(v) };
+                return 0;
+            }
+            Err(_) => EINVAL,
+        },
+        Ok(Err(e)) => errno(e, ESPIPE),
+        Err(_) => EIO,
+    };
+
+    -(e as c_int)
+}
+
+unsafe extern "C" fn close(ud: *mut c_void) -> c_int {
+    let f = unsafe { Box::from_raw(ud.cast::<Box<dyn FileIo>>()) };
+
+    // Drop inside catch_unwind since it may panic.
+    let r = catch_unwind(AssertUnwindSafe(move || {
+        let mut f = f;
+
+        f.flush()
+    }));
+
+    match r {
+        Ok(Ok(_)) => 0,
+        Ok(Err(e)) => -(errno(e, EIO) as c_int),
+        Err(_) => -(EIO as c_int),
+    }
+}
+
+/// # Safety
+/// `ud` must be returned from [`into_cookie()`] and not yet passed to Lua.
+pub(crate) unsafe fn drop_cookie(ud: *mut c_void) {
+    drop(unsafe { Box::from_raw(ud.cast::<Box<dyn FileIo>>()) });
+}
@@ -0,0 +1,555 @@
+use super::FileIo;
+use crate::ffi::{
+    zl_checkinteger, zl_checkstack, zl_filelines, zl_pushinteger, zl_pushvalue, zl_routestdio,
+    zl_setstdfile, zl_stringtonumber, zl_type,
+};
+use crate::state::RawState;
+use crate::{Context, Error, Frame, PositiveInt, Table, Type, UdRefMut, UserType, ud_value};
+use std::ffi::{CStr, c_int, c_void};
+use std::io::{ErrorKind, SeekFrom};
+
+/// Push a [`FileHandle`] for `io`.
+///
+/// # Panics
+/// If io library is not loaded.
+pub(crate) fn push_handle<P: Frame>(p: &mut P, io: impl FileIo + 'static, std: bool) {
+    // The stock io functions cannot work with our file so we need to replace it.
+    if unsafe { !zl_routestdio(p.state(), FileHandle::name().as_ptr(), is_closed) } {
+        panic!("io library is not loaded");
+    }
+
+    p.try_register_ud::<FileHandle>();
+
+    std::mem::forget(p.push_ud(FileHandle::new(io, std)));
+}
+
+/// Replace a standard stream with a [`FileHandle`] for `io`.
+///
+/// # Panics
+/// If io library is not loaded.
+pub(crate) fn set_std_handle<P: Frame>(p: &mut P, which: c_int, io: impl FileIo + 'static) {
+    push_handle(p, io, true);
+
+    unsafe { zl_setstdfile(p.state(), which) };
+}
+
+/// Lua file implemented on top of [`FileIo`] without `FILE`.
+///
+/// This provides the same methods as the Lua file for the platforms that cannot create a `FILE`
+/// from a Rust object.
+pub(crate) struct FileHandle {
+    io: Option<Box<dyn FileIo>>,
+    buf: Vec<u8>,
+    pos: usize,
+    std: bool,
+}
+
+impl FileHandle {
+    const BUF_SIZE: usize = 8192;
+
+    fn new(io: impl FileIo + 'static, std: bool) -> Self {
+        Self {
+            io: Some(Box::new(io)),
+            buf: Vec::new(),
+            pos: 0,
+            std,
+        }
+    }
+
+    fn read(cx: &mut Context) -> Result<(), Error> {
+        // Parse formats.
+        let mut formats = Vec::with_capacity(cx.args().max(2) as usize - 1);
+
+        for i in 2..=cx.args() {
+            formats.push(Format::parse(cx, PositiveInt::new(i).unwrap())?);
+        }
+
+        if formats.is_empty() {
+            formats.push(Format::Line(false));
+        }
+
+        // Read. We need to read everything before pushing since an error discard the previous
+        // results.
+        let mut f = Self::check(cx)?;
+        let mut values = Vec::with_capacity(formats.len());
+
+        for fmt in formats {
+            let r = match fmt {
+                Format::Number => f.read_number(),
+                Format::Line(keep) => f.read_line(keep),
+                Format::All => f.read_all().map(Some),
+                Format::Chars(n) => f.read_chars(n),
+            };
+
+            match r {
+                Ok(Some(v)) => values.push(Some((v, fmt == Format::Number))),
+                Ok(None) => {
+                    values.push(None);
+                    break;
+                }
+                Err(e) => return fail(cx, e),
+            }
+        }
+
+        drop(f);
+
+        // Push results.
+        unsafe { zl_checkstack(RawState::state(cx), values.len() as c_int) };
+
+        for v in values {
+            match v {
+                Some((mut v, true)) => {
+                    // Same as Lua, which return fail if the numeral is not valid.
+                    v.push(0);
+
+                    if unsafe { zl_stringtonumber(RawState::state(cx), v.as_ptr().cast()) == 0 } {
+                        cx.push_nil();
+                        break;
+                    }
+
+                    unsafe { cx.release_values(1) };
+                }
+                Some((v, false)) => drop(cx.push_str(v)),
+                None => drop(cx.push_nil()),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write(cx: &mut Context) -> Result<(), Error> {
+        let mut data = Vec::with_capacity(cx.args().max(1) as usize - 1);
+
+        for i in 2..=cx.args() {
+            data.push(cx.to_bytes(PositiveInt::new(i).unwrap()));
+        }
+
+        // Write.
+        let mut f = Self::check(cx)?;
+
+        f.discard();
+
+        for v in data {
+            if let Err(e) = f.io.as_mut().unwrap().write(v) {
+                return fail(cx, e);
+            }
+        }
+
+        drop(f);
+
+        // Return the file.
+        unsafe { zl_pushvalue(RawState::state(cx), 1) };
+        unsafe { cx.release_values(1) };
+
+        Ok(())
+    }
+
+    fn lines(cx: &mut Context) -> Result<(), Error> {
+        drop(Self::check(cx)?);
+
+        unsafe { zl_filelines(RawState::state(cx)) };
+        unsafe { cx.release_values(1) };
+
+        Ok(())
+    }
+
+    fn seek(cx: &mut Context) -> Result<(), Error> {
+        let whence = match cx.is_nil(PositiveInt::TWO) {
+            true => "cur",
+            false => cx.to_str(PositiveInt::TWO),
+        };
+
+        let off = match cx.is_nil(PositiveInt::THREE) {
+            true => 0,
+            false => unsafe { zl_checkinteger(RawState::state(cx), 3) },
+        };
+
+        // Seek.
+        let mut f = Self::check(cx)?;
+        let pos = match whence {
+            "set" => match u64::try_from(off) {
+                Ok(v) => SeekFrom::Start(v),
+                Err(_) => return fail(cx, ErrorKind::InvalidInput.into()),
+            },
+            "cur" => SeekFrom::Current(off - (f.buf.len() - f.pos) as i64),
+            "end" => SeekFrom::End(off),
+            v => {
+                return Err(Error::arg(
+                    PositiveInt::TWO,
+                    format!("invalid option '{v}'"),
+                ));
+            }
+        };
+
+        f.buf.clear();
+        f.pos = 0;
+
+        match f.io.as_mut().unwrap().seek(pos) {
+            Ok(v) => unsafe { zl_pushinteger(RawState::state(cx), v as i64) },
+            Err(e) => return fail(cx, e),
+        }
+
+        unsafe { cx.release_values(1) };
+
+        Ok(())
+    }
+
+    fn flush(cx: &mut Context) -> Result<(), Error> {
+        let mut f = Self::check(cx)?;
+
+        if let Err(e) = f.io.as_mut().unwrap().flush() {
+            return fail(cx, e);
+        }
+
+        cx.push_bool(true);
+
+        Ok(())
+    }
+
+    fn setvbuf(cx: &mut Context) -> Result<(), Error> {
+        // We don't buffer the output so the only thing we need to do is checking the mode.
+        match cx.to_str(PositiveInt::TWO) {
+            "no" | "full" | "line" => (),
+            v => {
+                return Err(Error::arg(
+                    PositiveInt::TWO,
+                    format!("invalid option '{v}'"),
+                ));
+            }
+        }
+
+        drop(Self::check(cx)?);
+
+        cx.push_bool(true);
+
+        Ok(())
+    }
+
+    fn close(cx: &mut Context) -> Result<(), Error> {
+        let mut f = Self::check(cx)?;
+
+        if f.std {
+            drop(f);
+            cx.push_nil();
+            cx.push_str("cannot close standard file");
+            return Ok(());
+        }
+
+        if let Err(e) = f.shutdown() {
+            return fail(cx, e);
+        }
+
+        cx.push_bool(true);
+
+        Ok(())
+    }
+
+    fn tostring(cx: &mut Context) -> Result<(), Error> {
+        let f = cx.to_ud::<Self>(PositiveInt::ONE).into_ud();
+        let s = match f.io {
+            Some(_) => format!("file ({f:p})"),
+            None => "file (closed)".into(),
+        };
+
+        cx.push_str(s);
+
+        Ok(())
+    }
+
+    fn to_close(cx: &mut Context) -> Result<(), Error> {
+        let mut f = cx.to_ud_mut::<Self>(PositiveInt::ONE);
+
+        if !f.std {
+            f.shutdown().ok();
+        }
+
+        Ok(())
+    }
+
+    fn check<'a>(cx: &mut Context<'a>) -> Result<UdRefMut<'a, Self>, Error> {
+        let f = cx.to_ud_mut::<Self>(PositiveInt::ONE);
+
+        match f.io {
+            Some(_) => Ok(f),
+            None => Err(Error::other(c"attempt to use a closed file")),
+        }
+    }
+
+    fn read_line(&mut self, keep: bool) -> Result<Option<Vec<u8>>, std::io::Error> {
+        let mut line = Vec::new();
+
+        loop {
+            let buf = self.fill()?;
+
+            if buf.is_empty() {
+                return Ok((!line.is_empty()).then_some(line));
+            }
+
+            match buf.iter().position(|&b| b == b'\n') {
+                Some(i) => {
+                    line.extend_from_slice(&buf[..(i + usize::from(keep))]);
+                    self.pos += i + 1;
+                    return Ok(Some(line));
+                }
+                None => {
+                    let n = buf.len();
+
+                    line.extend_from_slice(buf);
+                    self.pos += n;
+                }
+            }
+        }
+    }
+
+    fn read_all(&mut self) -> Result<Vec<u8>, std::io::Error> {
+        let mut data = Vec::new();
+
+        loop {
+            let buf = self.fill()?;
+
+            if buf.is_empty() {
+                return Ok(data);
+            }
+
+            let n = buf.len();
+
+            data.extend_from_slice(buf);
+            self.pos += n;
+        }
+    }
+
+    fn read_chars(&mut self, n: usize) -> Result<Option<Vec<u8>>, std::io::Error> {
+        // Zero is a test for end of file.
+        if n == 0 {
+            return Ok((!self.fill()?.is_empty()).then(Vec::new));
+        }
+
+        let mut data = Vec::new();
+
+        while data.len() < n {
+            let buf = self.fill()?;
+
+            if buf.is_empty() {
+                break;
+            }
+
+            let n = buf.len().min(n - data.len());
+
+            data.extend_from_slice(&buf[..n]);
+            self.pos += n;
+        }
+
+        Ok((!data.is_empty()).then_some(data))
+    }
+
+    fn read_number(&mut self) -> Result<Option<Vec<u8>>, std::io::Error> {
+        // This is the same algorithm as Lua, which read the longest prefix of a valid numeral.
+        let mut num = Number::default();
+
+        while self.peek()?.is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+
+        num.test(self, b"+-")?;
+
+        let mut hex = false;
+        let mut count = 0;
+
+        if num.test(self, b"0")? {
+            match num.test(self, b"xX")? {
+                true => hex = true,
+                false => count = 1,
+            }
+        }
+
+        count += num.digits(self, hex)?;
+
+        if num.test(self, b".")? {
+            count += num.digits(self, hex)?;
+        }
+
+        if count > 0 && num.test(self, if hex { b"pP" } else { b"eE" })? {
+            num.test(self, b"+-")?;
+            num.digits(self, false)?;
+        }
+
+        Ok(Some(num.buf))
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>, std::io::Error> {
+        Ok(self.fill()?.first().copied())
+    }
+
+    fn fill(&mut self) -> Result<&[u8], std::io::Error> {
+        if self.pos == self.buf.len() {
+            self.buf.resize(Self::BUF_SIZE, 0);
+            self.pos = 0;
+
+            match self.io.as_mut().unwrap().read(&mut self.buf) {
+                Ok(n) => self.buf.truncate(n),
+                Err(e) => {
+                    self.buf.clear();
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(&self.buf[self.pos..])
+    }
+
+    /// Discard the unread data so the next write happen at the current position.
+    fn discard(&mut self) {
+        let n = self.buf.len() - self.pos;
+
+        if n > 0 {
+            self.io
+                .as_mut()
+                .unwrap()
+                .seek(SeekFrom::Current(-(n as i64)))
+                .ok();
+        }
+
+        self.buf.clear();
+        self.pos = 0;
+    }
+
+    fn shutdown(&mut self) -> Result<(), std::io::Error> {
+        self.buf.clear();
+        self.pos = 0;
+
+        match self.io.take() {
+            Some(mut v) => v.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for FileHandle {
+    fn drop(&mut self) {
+        self.shutdown().ok();
+    }
+}
+
+impl UserType for FileHandle {
+    fn name() -> &'static CStr {
+        c"zl.file"
+    }
+
+    fn setup<P: Frame>(meta: &mut Table<P>) {
+        meta.set(c"__index").push_fn(|cx| {
+            match cx.to_str(PositiveInt::TWO) {
+                "close" => drop(cx.push_fn(Self::close)),
+                "flush" => drop(cx.push_fn(Self::flush)),
+                "lines" => drop(cx.push_fn(Self::lines)),
+                "read" => drop(cx.push_fn(Self::read)),
+                "seek" => drop(cx.push_fn(Self::seek)),
+                "setvbuf" => drop(cx.push_fn(Self::setvbuf)),
+                "write" => drop(cx.push_fn(Self::write)),
+                _ => drop(cx.push_nil()),
+            }
+
+            Ok(())
+        });
+
+        meta.set(c"__close").push_fn(Self::to_close);
+        meta.set(c"__tostring").push_fn(Self::tostring);
+    }
+}
+
+/// Format of `file:read`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Number,
+    Line(bool),
+    All,
+    Chars(usize),
+}
+
+impl Format {
+    fn parse(cx: &mut Context, n: PositiveInt) -> Result<Self, Error> {
+        if unsafe { zl_type(RawState::state(cx), n.get()) == Type::Number } {
+            let v = unsafe { zl_checkinteger(RawState::state(cx), n.get()) };
+
+            return Ok(Self::Chars(v.try_into().unwrap_or(usize::MAX)));
+        }
+
+        let v = cx.to_bytes(n);
+        let v = v.strip_prefix(b"*").unwrap_or(v);
+
+        match v.first() {
+            Some(b'n') => Ok(Self::Number),
+            Some(b'l') => Ok(Self::Line(false)),
+            Some(b'L') => Ok(Self::Line(true)),
+            Some(b'a') => Ok(Self::All),
+            _ => Err(Error::arg(n, c"invalid format")),
+        }
+    }
+}
+
+/// Numeral being read by `file:read('n')`.
+#[derive(Default)]
+struct Number {
+    buf: Vec<u8>,
+    overflow: bool,
+}
+
+impl Number {
+    /// Maximum length of a numeral, which is the same as Lua.
+    const MAX: usize = 200;
+
+    fn test(&mut self, f: &mut FileHandle, set: &[u8]) -> Result<bool, std::io::Error> {
+        match f.peek()? {
+            Some(b) if set.contains(&b) => Ok(self.push(f, b)),
+            _ => Ok(false),
+        }
+    }
+
+    fn digits(&mut self, f: &mut FileHandle, hex: bool) -> Result<usize, std::io::Error> {
+        let mut n = 0;
+
+        while let Some(b) = f.peek()? {
+            let ok = match hex {
+                true => b.is_ascii_hexdigit(),
+                false => b.is_ascii_digit(),
+            };
+
+            if !ok || !self.push(f, b) {
+                break;
+            }
+
+            n += 1;
+        }
+
+        Ok(n)
+    }
+
+    fn push(&mut self, f: &mut FileHandle, b: u8) -> bool {
+        // Lua invalidate the whole numeral if it is too long.
+        if self.overflow {
+            return false;
+        } else if self.buf.len() == Self::MAX {
+            self.buf.clear();
+            self.overflow = true;
+            return false;
+        }
+
+        self.buf.push(b);
+        f.pos += 1;
+
+        true
+    }
+}
+
+fn fail(cx: &mut Context, e: std::io::Error) -> Result<(), Error> {
+    cx.push_nil();
+    cx.push_str(e.to_string());
+
+    unsafe { zl_pushinteger(RawState::state(cx), e.raw_os_error().unwrap_or(0).into()) };
+    unsafe { cx.release_values(1) };
+
+    Ok(())
+}
+
+unsafe extern "C" fn is_closed(ud: *mut c_void) -> bool {
+    unsafe { (*ud_value::<FileHandle>(ud.cast())).io.is_none() }
+}
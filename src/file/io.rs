@@ -0,0 +1,72 @@
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
+
+/// Rust object behind a Lua file.
+pub(crate) trait FileIo {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
+    fn write(&mut self, buf: &[u8]) -> Result<(), Error>;
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Error>;
+    fn flush(&mut self) -> Result<(), Error>;
+}
+
+/// [`FileIo`] for [`Read`].
+pub(crate) struct Reader<T>(pub T);
+
+impl<T: Read> FileIo for Reader<T> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.0.read(buf)
+    }
+
+    fn write(&mut self, _: &[u8]) -> Result<(), Error> {
+        Err(Error::new(ErrorKind::Unsupported, "file is not writable"))
+    }
+
+    fn seek(&mut self, _: SeekFrom) -> Result<u64, Error> {
+        Err(Error::new(ErrorKind::Unsupported, "file is not seekable"))
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// [`FileIo`] for [`Write`].
+pub(crate) struct Writer<T>(pub T);
+
+impl<T: Write> FileIo for Writer<T> {
+    fn read(&mut self, _: &mut [u8]) -> Result<usize, Error> {
+        Err(Error::new(ErrorKind::Unsupported, "file is not readable"))
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.0.write_all(buf)
+    }
+
+    fn seek(&mut self, _: SeekFrom) -> Result<u64, Error> {
+        Err(Error::new(ErrorKind::Unsupported, "file is not seekable"))
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.0.flush()
+    }
+}
+
+/// [`FileIo`] for an object that implement [`Read`], [`Write`] and [`Seek`].
+pub(crate) struct Stream<T>(pub T);
+
+impl<T: Read + Write + Seek> FileIo for Stream<T> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.0.read(buf)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.0.write_all(buf)
+    }
+
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Error> {
+        self.0.seek(pos)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.0.flush()
+    }
+}
@@ -0,0 +1,237 @@
+#[cfg(any(not(unix), test))]
+pub(crate) use self::handle::*;
+pub(crate) use self::io::*;
+
+#[cfg(unix)]
+use self::cookie::{VTABLE, drop_cookie, into_cookie};
+use crate::ffi::{zl_pop, zl_tolstringmeta};
+#[cfg(unix)]
+use crate::ffi::{zl_pushfile, zl_setstdio};
+use crate::state::RawState;
+use crate::{Context, Error, Frame};
+use std::cell::RefCell;
+use std::ffi::c_int;
+use std::io::Write;
+
+#[cfg(unix)]
+mod cookie;
+#[cfg(any(not(unix), test))]
+mod handle;
+mod io;
+
+/// Index of standard streams for [`set_stdio()`].
+pub(crate) const STDIN: c_int = 0;
+pub(crate) const STDOUT: c_int = 1;
+pub(crate) const STDERR: c_int = 2;
+
+/// Push a Lua file for `f`.
+///
+/// # Panics
+/// If io library is not loaded.
+#[cfg(unix)]
+pub(crate) fn push_file<P: Frame>(p: &mut P, f: impl FileIo + 'static) {
+    let f = into_cookie(f);
+
+    if unsafe { !zl_pushfile(p.state(), f, &VTABLE) } {
+        unsafe { drop_cookie(f) };
+        panic!("io library is not loaded");
+    }
+}
+
+/// Push a Lua file for `f`.
+///
+/// # Panics
+/// If io library is not loaded.
+#[cfg(not(unix))]
+pub(crate) fn push_file<P: Frame>(p: &mut P, f: impl FileIo + 'static) {
+    push_handle(p, f, false);
+}
+
+/// Replace a standard stream with `f`.
+///
+/// # Panics
+/// If io library is not loaded.
+#[cfg(unix)]
+pub(crate) fn set_stdio<P: Frame>(p: &mut P, which: c_int, f: impl FileIo + 'static) {
+    let f = into_cookie(f);
+
+    if unsafe { !zl_setstdio(p.state(), which, f, &VTABLE) } {
+        unsafe { drop_cookie(f) };
+        panic!("io library is not loaded");
+    }
+}
+
+/// Replace a standard stream with `f`.
+///
+/// # Panics
+/// If io library is not loaded.
+#[cfg(not(unix))]
+pub(crate) fn set_stdio<P: Frame>(p: &mut P, which: c_int, f: impl FileIo + 'static) {
+    set_std_handle(p, which, f);
+}
+
+/// Implementation of `print` that write to `w`.
+pub(crate) fn print<W: Write>(cx: &mut Context, w: &RefCell<W>) -> Result<(), Error> {
+    // Convert arguments to string. We need to do this before borrowing the writer since
+    // __tostring can call print.
+    let mut buf = Vec::new();
+
+    for i in 1..=cx.args() {
+        let mut len = 0;
+        let s = unsafe { zl_tolstringmeta(RawState::state(cx), i, &mut len) };
+
+        if i > 1 {
+            buf.push(b'\t');
+        }
+
+        buf.extend_from_slice(unsafe { std::slice::from_raw_parts(s.cast(), len) });
+
+        unsafe { zl_pop(RawState::state(cx), 1) };
+    }
+
+    buf.push(b'\n');
+
+    // Write.
+    let mut w = w.borrow_mut();
+
+    w.write_all(&buf)
+        .and_then(|_| w.flush())
+        .map_err(|e| Error::with_source("failed to write output", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Reader, STDERR, STDIN, STDOUT, Stream, Writer, push_handle, set_std_handle};
+    use crate::ffi::zl_setglobal;
+    use crate::state::RawState;
+    use crate::{ChunkType, Frame, Lua};
+    use std::cell::RefCell;
+    use std::io::{Cursor, Write};
+    use std::rc::Rc;
+
+    #[derive(Default, Clone)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn print() {
+        let mut lua = Lua::new(None).unwrap();
+        let out = Output::default();
+
+        lua.require_base();
+        lua.set_print(out.clone());
+
+        let f = lua
+            .load(None, ChunkType::Text, "print(1, 'a', nil)\nprint()")
+            .unwrap();
+
+        assert!(f.call().is_ok());
+        assert_eq!(*out.0.borrow(), b"1\ta\tnil\n\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stdio() {
+        let mut lua = Lua::new(None).unwrap();
+        let out = Output::default();
+        let err = Output::default();
+
+        lua.require_base();
+        lua.require_io(true);
+        lua.set_stdin(Cursor::new(b"foo\nbar"));
+        lua.set_stdout(out.clone());
+        lua.set_stderr(err.clone());
+        lua.set_global(c"f").push_file(Cursor::new(Vec::new()));
+
+        let chunk = "io.write(io.read('l'), io.read('l'))\n\
+            io.stderr:write('baz')\n\
+            f:write('hello')\n\
+            f:seek('set')\n\
+            io.write(f:read('a'))\n\
+            assert(f:close())";
+        let f = lua.load(None, ChunkType::Text, chunk).unwrap();
+
+        assert!(f.call().is_ok());
+        assert_eq!(*out.0.borrow(), b"foobarhello");
+        assert_eq!(*err.0.borrow(), b"baz");
+
+        // Panic should be reported as an I/O error.
+        lua.set_stderr(Panic);
+
+        let f = lua
+            .load(None, ChunkType::Text, "assert(not io.stderr:write('x'))")
+            .unwrap();
+
+        assert!(f.call().is_ok());
+    }
+
+    #[test]
+    fn handle() {
+        let mut lua = Lua::new(None).unwrap();
+        let out = Output::default();
+        let err = Output::default();
+        let input = Cursor::new(b"foo\nbar\n12 0x1p4 -3.5e1 x");
+
+        lua.require_base();
+        lua.require_io(true);
+
+        set_std_handle(&mut lua, STDIN, Reader(input));
+        set_std_handle(&mut lua, STDOUT, Writer(out.clone()));
+        set_std_handle(&mut lua, STDERR, Writer(err.clone()));
+        push_handle(&mut lua, Stream(Cursor::new(Vec::new())), false);
+
+        unsafe { zl_setglobal(lua.state(), c"f".as_ptr()) };
+
+        let chunk = "io.write(io.read('l'), io.read('L'))\n\
+            assert(io.read('n') == 12)\n\
+            local a, b, c = io.read('n', 'n', 'n')\n\
+            assert(a == 16 and b == -35 and c == nil)\n\
+            assert(not io.stdin:write('x'))\n\
+            io.stderr:write('baz', 1)\n\
+            assert(io.type(f) == 'file' and io.type(io.stdout) == 'file' and io.type(1) == nil)\n\
+            f:write('hello\\nworld')\n\
+            assert(f:seek('set', 1) == 1)\n\
+            assert(f:read(2) == 'el')\n\
+            f:write('L')\n\
+            assert(f:seek('cur') == 4)\n\
+            f:seek('set')\n\
+            for l in f:lines() do io.write('[', l, ']') end\n\
+            assert(f:read(0) == nil and f:read('a') == '')\n\
+            io.output(f)\n\
+            io.write('!')\n\
+            assert(io.output() == f)\n\
+            assert(not io.stdout:close())\n\
+            assert(io.close())\n\
+            assert(io.type(f) == 'closed file' and tostring(f) == 'file (closed)')\n\
+            assert(not pcall(f.read, f))";
+        let f = lua.load(None, ChunkType::Text, chunk).unwrap();
+
+        f.call().ok().unwrap();
+
+        assert_eq!(*out.0.borrow(), b"foobar\n[helLo][world]");
+        assert_eq!(*err.0.borrow(), b"baz1");
+    }
+
+    #[cfg(unix)]
+    struct Panic;
+
+    #[cfg(unix)]
+    impl Write for Panic {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            panic!("write");
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+}
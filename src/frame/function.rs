@@ -21,3 +21,13 @@ where
         Err(e) => cx.raise(e),
     }
 }
+
+/// Returns [`invoker()`] for a zero-sized `F` without pushing anything.
+pub fn invoker_of<F>(_: &F) -> unsafe extern "C-unwind" fn(*mut lua_State) -> c_int
+where
+    F: Fn(&mut Context<NonYieldable>) -> Result<(), Error> + 'static,
+{
+    const { assert!(size_of::<F>() == 0) };
+
+    invoker::<F>
+}
@@ -1,23 +1,33 @@
 pub use self::r#yield::*;
 
 use self::r#async::async_invoker;
-use self::function::invoker;
-use self::userdata::{finalizer, push_metatable};
+use self::function::{invoker, invoker_of};
+use self::userdata::{finalizer, push_metatable, ud_finalizer};
+use crate::UserData;
+use crate::cache::load_cached;
 use crate::convert::IntoLua;
 use crate::ffi::{
-    ZL_LOADED_TABLE, ZL_REGISTRYINDEX, zl_checkstack, zl_createtable, zl_getfield, zl_getsubtable,
-    zl_load, zl_newmetatable, zl_newuserdatauv, zl_pop, zl_pushboolean, zl_pushcclosure,
-    zl_pushlstring, zl_pushnil, zl_require_base, zl_require_coroutine, zl_require_io,
-    zl_require_math, zl_require_os, zl_require_string, zl_require_table, zl_require_utf8,
-    zl_setfield, zl_setmetatable,
+    LUA_ERRMEM, LUA_OK, ZL_LOADED_TABLE, ZL_PRELOAD_TABLE, ZL_REGISTRYINDEX, zl_checkstack,
+    zl_createtable, zl_getfield, zl_getsearchers, zl_getsubtable, zl_load, zl_loadbuffer, zl_loadx,
+    zl_modulefree, zl_newmetatable, zl_newuserdatauv, zl_pop, zl_pushboolean, zl_pushcclosure,
+    zl_pushlstring, zl_pushnil, zl_rawlen, zl_require_base, zl_require_coroutine, zl_require_debug,
+    zl_require_io, zl_require_math, zl_require_os, zl_require_package, zl_require_string,
+    zl_require_table, zl_require_utf8, zl_sandbox, zl_setclock, zl_setfield, zl_setmetatable,
+    zl_textonly, zl_tolstring,
 };
+use crate::file::{Reader, STDERR, STDIN, STDOUT, Stream, Writer, print, push_file, set_stdio};
+use crate::package::search;
+use crate::reader::{ChunkReader, read as read_chunk, read_to_end};
 use crate::state::RawState;
+use crate::syntax::chunk_id;
 use crate::{
-    Bool, ChunkType, Context, Error, Function, GlobalSetter, Iter, ModuleBuilder, Nil,
-    NonYieldable, OwnedUd, PositiveInt, Str, TYPE_ID, Table, Type, UserType, Yieldable, is_boxed,
+    AsyncRead, Bool, ChunkType, Context, Error, Function, GlobalSetter, Iter, LoadError,
+    ModuleBuilder, Nil, NonYieldable, OwnedUd, PositiveInt, SafeOs, Searcher, Str, SyntaxError,
+    TYPE_ID, Table, Type, UdCell, UserType, Yieldable, is_boxed,
 };
 use std::any::{TypeId, type_name};
-use std::ffi::CStr;
+use std::cell::RefCell;
+use std::ffi::{CStr, c_int};
 use std::iter::Fuse;
 use std::mem::ManuallyDrop;
 use std::path::Path;
@@ -101,11 +111,8 @@ pub trait Frame: RawState {
         unsafe { zl_setfield(self.state(), -2, TYPE_ID.as_ptr()) };
 
         // Set finalizer.
-        if is_boxed::<T>() {
-            unsafe { zl_pushcclosure(self.state(), finalizer::<Box<T>>, 0) };
-            unsafe { zl_setfield(self.state(), -2, c"__gc".as_ptr()) };
-        } else if std::mem::needs_drop::<T>() {
-            unsafe { zl_pushcclosure(self.state(), finalizer::<T>, 0) };
+        if is_boxed::<T>() || std::mem::needs_drop::<T>() {
+            unsafe { zl_pushcclosure(self.state(), ud_finalizer::<T>, 0) };
             unsafe { zl_setfield(self.state(), -2, c"__gc".as_ptr()) };
         }
 
@@ -121,13 +128,18 @@ pub trait Frame: RawState {
     ///
     /// If this library already loaded this simply return it.
     ///
-    /// This use `luaL_requiref` + `luaopen_base` under the hood.
+    /// This use `luaL_requiref` + `luaopen_base` under the hood. `load`, `loadfile` and `dofile`
+    /// are replaced to always load a chunk in text mode unless
+    /// [`Lua::allow_binary()`](crate::Lua::allow_binary()) has been called.
     ///
     /// # Errors
     /// If memory is not enough.
     #[inline(always)]
     fn require_base(&mut self) -> Table<Self> {
+        let binary = self.extra1().binary.as_ptr().cast_const();
+
         unsafe { zl_require_base(self.state()) };
+        unsafe { zl_textonly(self.state(), binary) };
         unsafe { Table::new(self) }
     }
 
@@ -147,21 +159,93 @@ pub trait Frame: RawState {
         unsafe { Table::new(self) }
     }
 
+    /// Load [debug library](https://www.lua.org/manual/5.4/manual.html#6.10).
+    ///
+    /// If this library already loaded this simply return it. Note that this library can break
+    /// the safety of Zero Lua (e.g. `debug.setlocal` and `debug.setuservalue`) so it should be
+    /// used only for debugging.
+    ///
+    /// This use `luaL_requiref` + `luaopen_debug` under the hood.
+    ///
+    /// # Errors
+    /// If memory is not enough.
+    #[inline(always)]
+    fn require_debug(&mut self, global: bool) -> Table<Self> {
+        unsafe { zl_require_debug(self.state(), global) };
+        unsafe { Table::new(self) }
+    }
+
     #[inline(always)]
     fn require_io(&mut self, global: bool) -> Table<Self> {
         unsafe { zl_require_io(self.state(), global) };
         unsafe { Table::new(self) }
     }
 
-    #[inline(always)]
+    /// In deterministic mode `math.random` will be seeded with the value from
+    /// [`LuaBuilder::deterministic()`](crate::LuaBuilder::deterministic()) when this library is
+    /// loaded for the first time.
     fn require_math(&mut self, global: bool) -> Table<Self> {
-        unsafe { zl_require_math(self.state(), global) };
+        let seed = self.extra1().random_seed;
+        let seed = match &seed {
+            Some(v) => v,
+            None => null(),
+        };
+
+        unsafe { zl_require_math(self.state(), global, seed) };
         unsafe { Table::new(self) }
     }
 
-    #[inline(always)]
+    /// In deterministic mode `os.time`, `os.clock` and `os.date` will use [`Clock`] from
+    /// [`LuaBuilder::deterministic()`](crate::LuaBuilder::deterministic()) and `os.date` will
+    /// always use UTC.
     fn require_os(&mut self, global: bool) -> Table<Self> {
-        unsafe { zl_require_os(self.state(), global) };
+        let loaded = unsafe { zl_require_os(self.state(), global) };
+
+        if loaded && self.extra1().clock.is_some() {
+            let mut t = unsafe { Table::new(self) };
+
+            t.set(c"clock").push_fn(crate::clock::clock);
+
+            unsafe { zl_pushcclosure(t.state(), invoker_of(&crate::clock::now), 0) };
+            unsafe { zl_setclock(t.state()) };
+
+            t
+        } else {
+            unsafe { Table::new(self) }
+        }
+    }
+
+    /// Load [OS library](https://www.lua.org/manual/5.4/manual.html#6.9) with the functions that
+    /// access the host controlled by `opts`.
+    ///
+    /// `os.exit` will raise an error with `exit requested with code N` as a message instead of
+    /// terminating the process. Use [`Lua::take_exit_code()`](crate::Lua::take_exit_code()) to
+    /// get the code. Note that Lua code can still catch this error with `pcall`.
+    ///
+    /// If this library already loaded the functions will be replaced.
+    fn require_safe_os(&mut self, global: bool, opts: SafeOs) -> Table<Self> {
+        let mut t = self.require_os(global);
+
+        opts.install(&mut t);
+
+        t
+    }
+
+    /// Load [package library](https://www.lua.org/manual/5.4/manual.html#6.3), which provides
+    /// `require` function.
+    ///
+    /// If this library already loaded this simply return it. Specify `false` for `native` to
+    /// disable loading C libraries (`package.loadlib`, `package.cpath` and its searchers), which
+    /// should be the case for a sandboxed state. Use [`Frame::add_searcher()`] to provide modules
+    /// from Rust.
+    ///
+    /// This use `luaL_requiref` + `luaopen_package` under the hood.
+    ///
+    /// # Errors
+    /// If memory is not enough.
+    #[inline(always)]
+    fn require_package(&mut self, global: bool, native: bool) -> Table<Self> {
+        unsafe { zl_require_package(self.state(), global, native) };
         unsafe { Table::new(self) }
     }
 
@@ -183,18 +267,75 @@ pub trait Frame: RawState {
         unsafe { Table::new(self) }
     }
 
+    /// Append `s` to `package.searchers`.
+    ///
+    /// # Panics
+    /// If package library is not loaded. See [`Frame::require_package()`].
+    fn add_searcher<T: Searcher>(&mut self, s: T) {
+        if unsafe { !zl_getsearchers(self.state()) } {
+            panic!("package library is not loaded");
+        }
+
+        let n = unsafe { zl_rawlen(self.state(), -1) + 1 };
+        let mut t = unsafe { Table::new(self) };
+
+        t.set(n).push_fn(move |cx| search(cx, &s));
+    }
+
+    /// Returns [`None`] if the module with the same name already loaded or one of its parent (e.g.
+    /// `game` for `game.physics`) was loaded with a value that is not a table.
+    ///
+    /// Specify `true` for `global` if you want to put the module to global environment too.
     #[inline(always)]
-    fn register_module<N: AsRef<CStr>>(&mut self, name: N) -> Option<ModuleBuilder<Self, N>> {
+    fn register_module<N: AsRef<CStr>>(
+        &mut self,
+        name: N,
+        global: bool,
+    ) -> Option<ModuleBuilder<Self, N>> {
         unsafe { zl_getsubtable(self.state(), ZL_REGISTRYINDEX, ZL_LOADED_TABLE) };
 
-        match unsafe { zl_getfield(self.state(), -1, name.as_ref().as_ptr()) } {
-            Type::None => unreachable!(),
-            Type::Nil => {
-                unsafe { zl_pop(self.state(), 1) };
-                Some(unsafe { ModuleBuilder::new(self, name) })
-            }
-            _ => None,
+        if unsafe { !zl_modulefree(self.state(), -1, name.as_ref().as_ptr()) } {
+            unsafe { zl_pop(self.state(), 1) };
+            return None;
         }
+
+        Some(unsafe { ModuleBuilder::new(self, name, global) })
+    }
+
+    /// Register a module that will be loaded by `loader` on the first `require` via
+    /// `package.preload`.
+    ///
+    /// The first value pushed to the [`Context`] will become the module. Returns `false` if the
+    /// module with the same name already loaded or registered.
+    fn register_lazy_module<F>(&mut self, name: &CStr, loader: F) -> bool
+    where
+        F: Fn(&mut Context<NonYieldable>) -> Result<(), Error> + 'static,
+    {
+        // Check if already loaded.
+        unsafe { zl_getsubtable(self.state(), ZL_REGISTRYINDEX, ZL_LOADED_TABLE) };
+        let ty = unsafe { zl_getfield(self.state(), -1, name.as_ptr()) };
+        unsafe { zl_pop(self.state(), 2) };
+
+        if ty != Type::Nil {
+            return false;
+        }
+
+        // Check if already registered.
+        unsafe { zl_getsubtable(self.state(), ZL_REGISTRYINDEX, ZL_PRELOAD_TABLE) };
+
+        if unsafe { zl_getfield(self.state(), -1, name.as_ptr()) } != Type::Nil {
+            unsafe { zl_pop(self.state(), 2) };
+            return false;
+        }
+
+        unsafe { zl_pop(self.state(), 1) };
+
+        // Register.
+        let mut t = unsafe { Table::new(self) };
+
+        t.set(name).push_fn(loader);
+
+        true
     }
 
     #[inline(always)]
@@ -206,7 +347,8 @@ pub trait Frame: RawState {
     ///
     /// This method use
     /// [luaL_loadbufferx](https://www.lua.org/manual/5.4/manual.html#luaL_loadbufferx) to load the
-    /// chunk.
+    /// chunk. Binary chunk will be rejected unless [`Lua::allow_binary()`](crate::Lua::allow_binary())
+    /// has been called.
     #[inline(always)]
     fn load(
         &mut self,
@@ -216,7 +358,10 @@ pub trait Frame: RawState {
     ) -> Result<Function<Self>, Str<Self>> {
         let name = name.map(|v| v.as_ptr()).unwrap_or(null());
         let chunk = chunk.as_ref();
-        let mode = ty.to_c_str();
+        let mode = match ty.to_c_str(self.extra1().binary.get()) {
+            Some(v) => v,
+            None => return Err(crate::binary_denied(self)),
+        };
 
         match unsafe {
             zl_load(
@@ -232,10 +377,60 @@ pub trait Frame: RawState {
         }
     }
 
+    /// Same as [`Self::load()`] but returns a structured error, which is useful for reporting
+    /// syntax errors to the user.
+    ///
+    /// Use [`Function::dump()`] or drop the function if you only want to check the chunk.
+    fn compile(
+        &mut self,
+        name: Option<&CStr>,
+        ty: ChunkType,
+        chunk: impl AsRef<[u8]>,
+    ) -> Result<Function<Self>, LoadError> {
+        let chunk = chunk.as_ref();
+        let mode = match ty.to_c_str(self.extra1().binary.get()) {
+            Some(v) => v,
+            None => return Err(LoadError::Binary),
+        };
+        let r = unsafe {
+            zl_loadbuffer(
+                self.state(),
+                name.map(|v| v.as_ptr()).unwrap_or(null()),
+                chunk.as_ptr().cast(),
+                chunk.len(),
+                mode.as_ptr(),
+            )
+        };
+
+        if r == LUA_OK {
+            return Ok(unsafe { Function::new(self) });
+        }
+
+        // Get message.
+        let msg = unsafe {
+            let mut len = 0;
+            let ptr = zl_tolstring(self.state(), -1, &mut len);
+            let msg = std::slice::from_raw_parts(ptr.cast(), len);
+
+            String::from_utf8_lossy(msg).into_owned()
+        };
+
+        unsafe { zl_pop(self.state(), 1) };
+
+        if r == LUA_ERRMEM {
+            return Err(LoadError::Memory);
+        }
+
+        Err(LoadError::Syntax(SyntaxError::parse(chunk_id(name), &msg)))
+    }
+
     /// Load a Lua chunk (AKA Lua code) from a file.
     ///
     /// Note that this method will load the whole content of `file` into memory before passing to
-    /// Lua.
+    /// Lua. Use [`Self::load_reader()`] to read the chunk on demand instead. The file will be read
+    /// from [`Vfs`](crate::Vfs) if one has been set with [`Lua::set_vfs()`](crate::Lua::set_vfs()).
+    /// The compiled chunk will be cached if a cache has been set with
+    /// [`Lua::set_chunk_cache()`](crate::Lua::set_chunk_cache()).
     fn load_file(
         &mut self,
         file: impl AsRef<Path>,
@@ -243,7 +438,14 @@ pub trait Frame: RawState {
     ) -> Result<Result<Function<Self>, Str<Self>>, std::io::Error> {
         // Read file.
         let file = file.as_ref();
-        let data = std::fs::read(file)?;
+        let vfs = self.extra1().vfs.borrow().clone();
+        let (data, mtime) = match vfs {
+            Some(v) => (v.read(file)?, None),
+            None => (
+                std::fs::read(file)?,
+                std::fs::metadata(file).and_then(|m| m.modified()).ok(),
+            ),
+        };
 
         // Get chunk name.
         let file = file.to_string_lossy();
@@ -257,7 +459,61 @@ pub trait Frame: RawState {
         // file path may contains interior NUL.
         let name = unsafe { CStr::from_ptr(name.as_ptr().cast()) };
 
-        Ok(self.load(Some(name), ty, data))
+        Ok(load_cached(self, name, mtime, ty, &data))
+    }
+
+    /// Load a Lua chunk (AKA Lua code) from `r`.
+    ///
+    /// Unlike [`Self::load_file()`] the chunk will be read on demand while Lua is compiling it.
+    /// Returns [`Err`] if reading from `r` failed, which take precedence over the syntax error.
+    fn load_reader<R: std::io::Read>(
+        &mut self,
+        name: Option<&CStr>,
+        ty: ChunkType,
+        r: R,
+    ) -> Result<Result<Function<Self>, Str<Self>>, std::io::Error> {
+        let name = name.map(|v| v.as_ptr()).unwrap_or(null());
+        let mode = match ty.to_c_str(self.extra1().binary.get()) {
+            Some(v) => v,
+            None => return Ok(Err(crate::binary_denied(self))),
+        };
+        let mut r = ChunkReader::new(r);
+        let ok = unsafe {
+            zl_loadx(
+                self.state(),
+                read_chunk::<R>,
+                (&raw mut r).cast(),
+                name,
+                mode.as_ptr(),
+            )
+        };
+
+        if let Some(e) = r.error {
+            unsafe { zl_pop(self.state(), 1) };
+            return Err(e);
+        }
+
+        match ok {
+            true => Ok(Ok(unsafe { Function::new(self) })),
+            false => Ok(Err(unsafe { Str::new(self) })),
+        }
+    }
+
+    /// Asynchronous version of [`Self::load_reader()`].
+    ///
+    /// Lua does not support compiling a chunk asynchronously so this will read the whole content
+    /// of `r` into memory before passing to Lua.
+    fn load_async_reader(
+        &mut self,
+        name: Option<&CStr>,
+        ty: ChunkType,
+        r: impl AsyncRead,
+    ) -> impl Future<Output = Result<Result<Function<Self>, Str<Self>>, std::io::Error>> {
+        async move {
+            let data = read_to_end(r).await?;
+
+            Ok(self.load(name, ty, data))
+        }
     }
 
     #[inline(always)]
@@ -287,6 +543,28 @@ pub trait Frame: RawState {
         unsafe { Table::new(self) }
     }
 
+    /// Push a new table contains only globals listed in `allowed`, which can be used as an
+    /// environment for [`Table::load_with_env()`].
+    ///
+    /// Each entry can be either a name of global (e.g. `print` or `string`) or a field of a library
+    /// (e.g. `string.format`). A library table listed as a whole will be shallow copied so the
+    /// sandbox cannot modify the original one. Any entry that does not exists will be ignored.
+    ///
+    /// Note that the metatable of string still references the original `string` library. `load`,
+    /// `loadfile` and `dofile` copied from [`Self::require_base()`] reject a binary chunk the same
+    /// as the global one.
+    fn push_sandbox(&mut self, allowed: &[&CStr]) -> Table<Self> {
+        let nrec = allowed.len().try_into().unwrap_or(c_int::MAX);
+
+        unsafe { zl_createtable(self.state(), 0, nrec) };
+
+        for name in allowed {
+            unsafe { zl_sandbox(self.state(), name.as_ptr()) };
+        }
+
+        unsafe { Table::new(self) }
+    }
+
     fn push_iter<T, I>(&mut self, v: T) -> Iter<Self>
     where
         T: IntoIterator<Item: IntoLua, IntoIter = I>,
@@ -337,25 +615,86 @@ pub trait Frame: RawState {
     fn push_ud<T: UserType>(&mut self, v: T) -> OwnedUd<Self, T> {
         // Create userdata.
         let nuvalue = T::user_values().map(|v| v.get()).unwrap_or(0).into();
-        let ptr = if is_boxed::<T>() {
-            let ptr = unsafe { zl_newuserdatauv(self.state(), size_of::<Box<T>>(), nuvalue) };
-            let ptr = ptr.cast::<Box<T>>();
+        let ud = if is_boxed::<T>() {
+            let size = size_of::<UdCell<Box<T>>>();
+            let ud = unsafe { zl_newuserdatauv(self.state(), size, nuvalue) };
 
-            unsafe { ptr.write(v.into()) };
-            unsafe { (*ptr).as_ref() as *const T }
+            unsafe { ud.cast::<UdCell<Box<T>>>().write(UdCell::new(v.into())) };
+            ud
         } else {
-            let ptr = unsafe { zl_newuserdatauv(self.state(), size_of::<T>(), nuvalue) };
-            let ptr = ptr.cast::<T>();
+            let ud = unsafe { zl_newuserdatauv(self.state(), size_of::<UdCell<T>>(), nuvalue) };
 
-            unsafe { ptr.write(v) };
-            ptr
+            unsafe { ud.cast::<UdCell<T>>().write(UdCell::new(v)) };
+            ud
         };
 
         // Set metatable.
         unsafe { push_metatable::<T>(self.state()) };
         unsafe { zl_setmetatable(self.state(), -2) };
 
-        unsafe { OwnedUd::new(self, ptr) }
+        unsafe { OwnedUd::new(self, ud) }
+    }
+
+    /// Push a Lua file (e.g. the one returned from `io.open`) that read, write and seek on `f`.
+    ///
+    /// The file is compatible with all file methods (e.g. `file:read` and `file:lines`). `f` will
+    /// be dropped when the file is closed or garbage collected.
+    ///
+    /// On Unix the file is a `FILE` created with `fopencookie` or `funopen`. On the other platforms
+    /// it is a userdata that implements the same methods, in which case `io.read`, `io.write`,
+    /// `io.lines`, `io.input`, `io.output`, `io.close`, `io.flush` and `io.type` will be replaced
+    /// with the functions that can work with it.
+    ///
+    /// # Panics
+    /// If io library is not loaded.
+    fn push_file<T>(&mut self, f: T) -> UserData<Self>
+    where
+        T: std::io::Read + std::io::Write + std::io::Seek + 'static,
+    {
+        push_file(self, Stream(f));
+
+        unsafe { UserData::new(self) }
+    }
+
+    /// Replace `print` function with the one that write to `w`.
+    fn set_print(&mut self, w: impl std::io::Write + 'static) {
+        let w = RefCell::new(w);
+
+        self.set_global(c"print").push_fn(move |cx| print(cx, &w));
+    }
+
+    /// Replace `io.stdin` and the default input file with `r`.
+    ///
+    /// The previous file will not be closed. See [`Frame::push_file()`] for the platform
+    /// differences.
+    ///
+    /// # Panics
+    /// If io library is not loaded.
+    fn set_stdin(&mut self, r: impl std::io::Read + 'static) {
+        set_stdio(self, STDIN, Reader(r));
+    }
+
+    /// Replace `io.stdout` and the default output file with `w`.
+    ///
+    /// The previous file will not be closed. Note that `print` will not use this file. Use
+    /// [`Frame::set_print()`] to redirect `print`. See [`Frame::push_file()`] for the platform
+    /// differences.
+    ///
+    /// # Panics
+    /// If io library is not loaded.
+    fn set_stdout(&mut self, w: impl std::io::Write + 'static) {
+        set_stdio(self, STDOUT, Writer(w));
+    }
+
+    /// Replace `io.stderr` with `w`.
+    ///
+    /// The previous file will not be closed. See [`Frame::push_file()`] for the platform
+    /// differences.
+    ///
+    /// # Panics
+    /// If io library is not loaded.
+    fn set_stderr(&mut self, w: impl std::io::Write + 'static) {
+        set_stdio(self, STDERR, Writer(w));
     }
 
     /// See [`Context`] for how to return some values to Lua.
@@ -1,5 +1,5 @@
 use crate::ffi::{lua_State, zl_getfield, zl_globalmetatable, zl_pop, zl_touserdata};
-use crate::{Type, UserType};
+use crate::{TAKEN, Type, UdCell, UserType, is_boxed};
 use std::any::{TypeId, type_name};
 use std::ffi::c_int;
 
@@ -12,6 +12,30 @@ where
     0
 }
 
+/// Finalizer for [`UserType`], which skip the value that was moved out.
+pub unsafe extern "C-unwind" fn ud_finalizer<T>(#[allow(non_snake_case)] L: *mut lua_State) -> c_int
+where
+    T: UserType,
+{
+    let ud = unsafe { zl_touserdata(L, 1) };
+
+    if is_boxed::<T>() {
+        let ud = ud.cast::<UdCell<Box<T>>>();
+
+        if unsafe { (*ud).borrow.get() != TAKEN } {
+            unsafe { std::ptr::drop_in_place(ud) };
+        }
+    } else {
+        let ud = ud.cast::<UdCell<T>>();
+
+        if unsafe { (*ud).borrow.get() != TAKEN } {
+            unsafe { std::ptr::drop_in_place(ud) };
+        }
+    }
+
+    0
+}
+
 /// # Panics
 /// If `T` is not registered.
 #[inline(never)]
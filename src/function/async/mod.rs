@@ -3,7 +3,9 @@ pub use self::result::*;
 use self::resume::Resume;
 use super::Ret;
 use crate::ffi::{LUA_OK, LUA_YIELD, lua_State, zl_pop};
+use crate::os::map_exit;
 use crate::state::RawState;
+use crate::transform::map_error;
 use crate::{Frame, Str};
 use std::cell::Cell;
 use std::ffi::c_int;
@@ -44,6 +46,9 @@ impl<'a, P: Frame> AsyncCall<'a, P> {
         'a: 'b,
     {
         let mut n = 0;
+
+        self.result.extra1().exit.set(None);
+
         let f = Resume::new(
             &mut self.result,
             &mut self.args,
@@ -54,10 +59,21 @@ impl<'a, P: Frame> AsyncCall<'a, P> {
 
         self.polled = true;
 
-        match f.await {
+        let r = f.await;
+
+        // The exit code may be set if the script catch the exit request.
+        if r == LUA_OK || r == LUA_YIELD {
+            self.result.extra1().exit.set(None);
+        }
+
+        match r {
             LUA_OK => unsafe { Ok(Async::Finish(Ret::new(&mut self.result, n))) },
             LUA_YIELD => unsafe { Ok(Async::Yield(Ret::new(&mut self.result, n))) },
-            _ => unsafe { Err(Str::new(&mut self.result)) },
+            _ => unsafe {
+                map_exit(self.result.state(), self.result.extra1());
+                map_error(self.result.state(), self.result.extra1());
+                Err(Str::new(&mut self.result))
+            },
         }
     }
 }
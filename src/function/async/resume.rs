@@ -1,5 +1,6 @@
 use super::{AsyncContext, PendingFuture, YieldValues};
 use crate::ffi::{LUA_YIELD, zl_pop, zl_resume, zl_touserdata};
+use crate::hook::clear_interrupt;
 use crate::state::RawState;
 use std::cell::Cell;
 use std::ffi::c_int;
@@ -75,6 +76,8 @@ impl<S: RawState> Future for Resume<'_, S> {
         drop(l);
 
         if r != LUA_YIELD {
+            unsafe { clear_interrupt(this.state.state(), this.state.extra1()) };
+
             return Poll::Ready(r);
         }
 
@@ -82,6 +85,7 @@ impl<S: RawState> Future for Resume<'_, S> {
         let cx = &mut cx as *mut AsyncContext as *mut u8;
 
         if *this.results != 3 || unsafe { zl_touserdata(this.state.state(), -1) != cx } {
+            unsafe { clear_interrupt(this.state.state(), this.state.extra1()) };
             return Poll::Ready(LUA_YIELD);
         }
 
@@ -100,6 +104,7 @@ impl<S: RawState> Future for Resume<'_, S> {
         match this.values.get() {
             YieldValues::None => Poll::Pending,
             YieldValues::FromThread(v) => {
+                unsafe { clear_interrupt(this.state.state(), this.state.extra1()) };
                 *this.results = v;
                 this.values.set(YieldValues::FromThread(0)); // Prevent double free on future side.
                 Poll::Ready(LUA_YIELD)
@@ -1,10 +1,17 @@
 pub use self::r#async::*;
 pub use self::result::*;
 
-use crate::ffi::{LUA_MULTRET, lua_State, zl_gettop, zl_pcall, zl_pop};
+use crate::ffi::{
+    LUA_MULTRET, lua_Debug, lua_State, zl_dump, zl_getinfo, zl_gettop, zl_pcall, zl_pop,
+    zl_pushvalue,
+};
+use crate::hook::clear_interrupt;
+use crate::os::map_exit;
 use crate::state::RawState;
-use crate::{AsyncThread, Frame, Lua, Str, Unknown};
-use std::ffi::c_int;
+use crate::transform::map_error;
+use crate::{AsyncThread, DebugInfo, Frame, Lua, Str, Unknown, Upvalues};
+use std::ffi::{c_int, c_void};
+use std::io::{ErrorKind, Write};
 
 mod r#async;
 mod result;
@@ -31,6 +38,62 @@ impl<'p, P: Frame> Function<'p, P> {
         }
     }
 
+    /// Returns information about this function.
+    ///
+    /// [`DebugInfo::current_line()`] and [`DebugInfo::name()`] always [`None`] since this function
+    /// is not active.
+    pub fn info(&mut self) -> DebugInfo {
+        let mut ar = lua_Debug::default();
+
+        unsafe { zl_pushvalue(self.state(), self.func) };
+        unsafe { zl_getinfo(self.state(), c">S".as_ptr(), &mut ar) };
+
+        unsafe { DebugInfo::new(&ar) }
+    }
+
+    /// Dump this function as a binary chunk, which can be loaded with [`ChunkType::Binary`](crate::ChunkType::Binary) after
+    /// [`Lua::allow_binary()`]. Specify `true` for `strip` to remove debug information.
+    ///
+    /// Returns [`None`] if this is not a Lua function.
+    pub fn dump(&mut self, strip: bool) -> Option<Vec<u8>> {
+        let mut buf = Vec::new();
+
+        match self.dump_to(&mut buf, strip) {
+            Ok(_) => Some(buf),
+            Err(_) => None,
+        }
+    }
+
+    /// Same as [`Self::dump()`] but write the binary chunk to `w`.
+    ///
+    /// Returns [`std::io::ErrorKind::InvalidInput`] if this is not a Lua function.
+    pub fn dump_to<W: Write>(&mut self, w: &mut W, strip: bool) -> std::io::Result<()> {
+        let mut data = (w, None);
+
+        unsafe { zl_pushvalue(self.state(), self.func) };
+
+        let r = unsafe { zl_dump(self.state(), write_dump::<W>, (&raw mut data).cast(), strip) };
+
+        unsafe { zl_pop(self.state(), 1) };
+
+        match (r, data.1) {
+            (0, _) => Ok(()),
+            (_, Some(e)) => Err(e),
+            (_, None) => Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "not a Lua function",
+            )),
+        }
+    }
+
+    /// Returns upvalues of this function.
+    #[inline(always)]
+    pub fn upvalues(&mut self) -> Upvalues<Self> {
+        let func = self.func;
+
+        unsafe { Upvalues::new(self, func) }
+    }
+
     #[inline(always)]
     pub fn into_unknown(mut self) -> Unknown<'p, P> {
         let p = self.parent.take().unwrap();
@@ -51,13 +114,24 @@ impl<'p> Function<'p, Lua> {
         // Call.
         let p = self.parent.take().unwrap();
 
-        if !unsafe { zl_pcall(p.state(), self.args, LUA_MULTRET, 0) } {
+        p.extra1().exit.set(None);
+
+        let r = unsafe { zl_pcall(p.state(), self.args, LUA_MULTRET, 0) };
+
+        unsafe { clear_interrupt(p.state(), p.extra1()) };
+
+        if !r {
+            unsafe { map_exit(p.state(), p.extra1()) };
+            unsafe { map_error(p.state(), p.extra1()) };
+
             return Err(unsafe { Str::new(p) });
         }
 
-        // Get results.
+        // Get results. The exit code may be set if the script catch the exit request.
         let l = unsafe { zl_gettop(p.state()) - (self.func - 1) };
 
+        p.extra1().exit.set(None);
+
         Ok(unsafe { Ret::new(p, l) })
     }
 }
@@ -67,7 +141,11 @@ impl<'p> Function<'p, AsyncThread> {
     /// dropped.
     #[inline(always)]
     pub fn into_async(mut self) -> AsyncCall<'p, AsyncThread> {
-        unsafe { AsyncCall::new(self.parent.take().unwrap(), self.args) }
+        let p = self.parent.take().unwrap();
+
+        p.sync_hook();
+
+        unsafe { AsyncCall::new(p, self.args) }
     }
 }
 
@@ -106,10 +184,29 @@ impl<'p, P: Frame> From<Function<'p, P>> for Unknown<'p, P> {
     }
 }
 
+/// Implementation of `lua_Writer` for [`Function::dump_to()`].
+unsafe extern "C-unwind" fn write_dump<W: Write>(
+    _: *mut lua_State,
+    p: *const c_void,
+    sz: usize,
+    ud: *mut c_void,
+) -> c_int {
+    let data = unsafe { &mut *ud.cast::<(&mut W, Option<std::io::Error>)>() };
+    let buf = unsafe { std::slice::from_raw_parts(p.cast::<u8>(), sz) };
+
+    match data.0.write_all(buf) {
+        Ok(_) => 0,
+        Err(e) => {
+            data.1 = Some(e);
+            1
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{ChunkType, Lua};
+    use crate::{ChunkType, LoadError, Lua};
 
     #[test]
     fn async_resume_complete_immediately() {
@@ -128,4 +225,66 @@ mod tests {
             assert_eq!(r.to_int(1).unwrap(), 5);
         });
     }
+
+    #[test]
+    fn dump() {
+        let mut lua = Lua::new(None).unwrap();
+        let chunk = lua
+            .load(None, ChunkType::Text, b"return 7")
+            .unwrap()
+            .dump(true)
+            .unwrap();
+
+        // Binary chunk should be rejected by default.
+        assert!(lua.load(None, ChunkType::Both, &chunk).is_err());
+
+        let mut e = match lua.load(None, ChunkType::Binary, &chunk) {
+            Ok(_) => panic!("unexpected success"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "loading a binary chunk is not allowed");
+        drop(e);
+
+        assert!(matches!(
+            lua.compile(None, ChunkType::Binary, &chunk),
+            Err(LoadError::Binary)
+        ));
+
+        // Including the one from Lua.
+        lua.require_base();
+        lua.set_global(c"chunk").push_str(&chunk);
+
+        let env = lua.push_sandbox(&[c"load", c"assert", c"chunk"]);
+        let code = "assert(not load(chunk))\n\
+            assert(not load(chunk, nil, 'b'))\n\
+            return load(chunk, nil, 'b', {})";
+        let f = env.load_with_env(None, ChunkType::Text, code).unwrap();
+        let mut r = f.call().unwrap();
+
+        assert!(r.to_bool(1).is_none());
+        assert_eq!(
+            r.to_bytes(2).unwrap(),
+            b"attempt to load a binary chunk (mode is 't')"
+        );
+        drop(r);
+
+        unsafe { lua.allow_binary() };
+
+        let f = lua.load(None, ChunkType::Binary, &chunk).unwrap();
+        let mut r = f.call().unwrap();
+
+        assert_eq!(r.to_int(1).unwrap(), 7);
+        drop(r);
+
+        let f = lua
+            .load(None, ChunkType::Text, "return load(chunk, nil, 'b')()")
+            .unwrap();
+        let mut r = f.call().unwrap();
+
+        assert_eq!(r.to_int(1).unwrap(), 7);
+        drop(r);
+
+        assert!(lua.push_fn(|_| Ok(())).dump(false).is_none());
+    }
 }
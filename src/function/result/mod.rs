@@ -1,4 +1,4 @@
-use crate::ffi::{zl_isnil, zl_tointegerx, zl_type};
+use crate::ffi::{zl_isnil, zl_toboolean, zl_tointegerx, zl_tolstring, zl_tonumberx, zl_type};
 use crate::{Frame, Type};
 use std::ffi::c_int;
 
@@ -47,6 +47,54 @@ impl<'a, P: Frame> Ret<'a, P> {
         if ok == 0 { None } else { Some(val) }
     }
 
+    /// Returns [`None`] if the value is not a boolean.
+    ///
+    /// `n` is one-based the same as function arguments.
+    ///
+    /// # Panics
+    /// If `n` is less than 1 or geater than [len](Self::len()).
+    #[inline(always)]
+    pub fn to_bool(&mut self, n: c_int) -> Option<bool> {
+        let i = self.index(n);
+
+        match unsafe { zl_type(self.parent.state(), i) } {
+            Type::Boolean => Some(unsafe { zl_toboolean(self.parent.state(), i) }),
+            _ => None,
+        }
+    }
+
+    /// `n` is one-based the same as function arguments.
+    ///
+    /// # Panics
+    /// If `n` is less than 1 or geater than [len](Self::len()).
+    #[inline(always)]
+    pub fn to_num(&mut self, n: c_int) -> Option<f64> {
+        let mut ok = 0;
+        let val = unsafe { zl_tonumberx(self.parent.state(), self.index(n), &mut ok) };
+
+        if ok == 0 { None } else { Some(val) }
+    }
+
+    /// Returns [`None`] if the value is not a string. Number will **not** be converted.
+    ///
+    /// `n` is one-based the same as function arguments.
+    ///
+    /// # Panics
+    /// If `n` is less than 1 or geater than [len](Self::len()).
+    #[inline(always)]
+    pub fn to_bytes(&mut self, n: c_int) -> Option<&[u8]> {
+        let i = self.index(n);
+
+        if unsafe { zl_type(self.parent.state(), i) } != Type::String {
+            return None;
+        }
+
+        let mut len = 0;
+        let ptr = unsafe { zl_tolstring(self.parent.state(), i, &mut len) };
+
+        Some(unsafe { std::slice::from_raw_parts(ptr.cast(), len) })
+    }
+
     /// `n` is one-based the same as function arguments.
     ///
     /// # Panics
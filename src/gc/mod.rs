@@ -0,0 +1,51 @@
+use crate::ffi::{LUA_GCGEN, LUA_GCINC, lua_State, zl_gc};
+use std::ffi::c_int;
+
+/// Mode of Lua garbage collector.
+///
+/// Zero on any parameter means keeping the current value. See
+/// [Lua manual](https://www.lua.org/manual/5.4/manual.html#2.5) for the meaning of each parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GcMode {
+    Incremental {
+        pause: c_int,
+        step_mul: c_int,
+        step_size: c_int,
+    },
+    Generational {
+        minor_mul: c_int,
+        major_mul: c_int,
+    },
+}
+
+impl GcMode {
+    /// Switch the collector of `state` to this mode and returns the previous mode.
+    ///
+    /// # Safety
+    /// `state` must be valid.
+    pub(crate) unsafe fn apply(self, state: *mut lua_State) -> GcKind {
+        let prev = match self {
+            Self::Incremental {
+                pause,
+                step_mul,
+                step_size,
+            } => unsafe { zl_gc(state, LUA_GCINC, pause, step_mul, step_size) },
+            Self::Generational {
+                minor_mul,
+                major_mul,
+            } => unsafe { zl_gc(state, LUA_GCGEN, minor_mul, major_mul, 0) },
+        };
+
+        match prev {
+            LUA_GCGEN => GcKind::Generational,
+            _ => GcKind::Incremental,
+        }
+    }
+}
+
+/// Kind of [`GcMode`] without its parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GcKind {
+    Incremental,
+    Generational,
+}
@@ -0,0 +1,146 @@
+use crate::ffi::{
+    LUA_HOOKCALL, LUA_HOOKCOUNT, LUA_HOOKLINE, LUA_HOOKRET, LUA_HOOKTAILCALL, lua_Debug, lua_State,
+    zl_getextraspace, zl_getinfo, zl_isyieldable,
+};
+use std::cell::Cell;
+use std::ffi::{CStr, c_int};
+use std::marker::PhantomData;
+
+/// Provides information about the event that trigger a hook.
+///
+/// The information about the running function is lazily loaded with `lua_getinfo` on the first
+/// access.
+pub struct HookContext<'a> {
+    state: *mut lua_State,
+    ar: *mut lua_Debug,
+    loaded: Cell<bool>,
+    yield_: bool,
+    phantom: PhantomData<&'a mut lua_Debug>,
+}
+
+impl HookContext<'_> {
+    /// # Safety
+    /// `ar` must be the one passed to the hook.
+    #[inline(always)]
+    pub(super) unsafe fn new(state: *mut lua_State, ar: *mut lua_Debug) -> Self {
+        Self {
+            state,
+            ar,
+            loaded: Cell::new(false),
+            yield_: false,
+            phantom: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn event(&self) -> HookEvent {
+        match unsafe { (*self.ar).event } {
+            LUA_HOOKCALL => HookEvent::Call,
+            LUA_HOOKRET => HookEvent::Return,
+            LUA_HOOKLINE => HookEvent::Line,
+            LUA_HOOKCOUNT => HookEvent::Count,
+            LUA_HOOKTAILCALL => HookEvent::TailCall,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the current line of the running function or [`None`] if no line information is
+    /// available (e.g. the function is not a Lua function).
+    #[inline(always)]
+    pub fn current_line(&self) -> Option<c_int> {
+        Some(self.info().currentline).filter(|&v| v > 0)
+    }
+
+    /// Returns the source of the chunk that created the running function.
+    ///
+    /// This is the chunk name that was passed to [`Frame::load()`](crate::Frame::load()) or the
+    /// chunk itself if no name was specified.
+    #[inline(always)]
+    pub fn source(&self) -> &[u8] {
+        let ar = self.info();
+
+        unsafe { std::slice::from_raw_parts(ar.source.cast(), ar.srclen) }
+    }
+
+    /// Returns a "printable" version of [`Self::source()`] to be used in error messages.
+    #[inline(always)]
+    pub fn short_src(&self) -> &CStr {
+        unsafe { CStr::from_ptr(self.info().short_src.as_ptr()) }
+    }
+
+    /// Returns `Lua` if the running function is a Lua function, `C` if it is a Rust function and
+    /// `main` if it is the main part of a chunk.
+    #[inline(always)]
+    pub fn what(&self) -> &CStr {
+        unsafe { CStr::from_ptr(self.info().what) }
+    }
+
+    /// Returns a reasonable name for the running function or [`None`] if Lua cannot find one.
+    #[inline(always)]
+    pub fn name(&self) -> Option<&CStr> {
+        let v = self.info().name;
+
+        if v.is_null() {
+            None
+        } else {
+            Some(unsafe { CStr::from_ptr(v) })
+        }
+    }
+
+    /// Returns how the name from [`Self::name()`] was found (e.g. `global`, `local`, `method`,
+    /// `field`, `upvalue`). Returns an empty string if no name was found.
+    #[inline(always)]
+    pub fn name_what(&self) -> &CStr {
+        unsafe { CStr::from_ptr(self.info().namewhat) }
+    }
+
+    /// Request the running Lua thread to yield after the hook returns.
+    ///
+    /// Returns `false` if the running Lua thread cannot yield, which is the case when the event is
+    /// neither [`HookEvent::Line`] nor [`HookEvent::Count`] or the code is not running by
+    /// [`AsyncThread`](crate::AsyncThread). The caller will receive [`Async::Yield`](crate::Async)
+    /// without any values.
+    pub fn request_yield(&mut self) -> bool {
+        // A coroutine created by Lua can also yield but it is not the one that we are resuming.
+        // The extra space of AsyncThread contains a pointer to its context while resuming.
+        let resuming = unsafe { !zl_getextraspace(self.state).add(1).read().is_null() };
+
+        if !matches!(self.event(), HookEvent::Line | HookEvent::Count)
+            || !resuming
+            || !unsafe { zl_isyieldable(self.state) }
+        {
+            return false;
+        }
+
+        self.yield_ = true;
+
+        true
+    }
+
+    #[inline(always)]
+    pub(super) fn take_yield(&mut self) -> bool {
+        std::mem::take(&mut self.yield_)
+    }
+
+    #[inline(always)]
+    fn info(&self) -> &lua_Debug {
+        // We need to load everything at once since the returned reference will be alive after
+        // this.
+        if !self.loaded.get() {
+            unsafe { zl_getinfo(self.state, c"nSl".as_ptr(), self.ar) };
+            self.loaded.set(true);
+        }
+
+        unsafe { &*self.ar }
+    }
+}
+
+/// Type of event that trigger a hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    Call,
+    Return,
+    Line,
+    Count,
+    TailCall,
+}
@@ -0,0 +1,24 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Handle to interrupt a running Lua code from any thread.
+///
+/// Use [`Lua::interrupt_handle()`](crate::Lua::interrupt_handle()) or
+/// [`AsyncLua::interrupt_handle()`](crate::AsyncLua::interrupt_handle()) to get this handle.
+#[derive(Clone)]
+pub struct InterruptHandle(Arc<AtomicBool>);
+
+impl InterruptHandle {
+    pub(crate) fn new(flag: Arc<AtomicBool>) -> Self {
+        Self(flag)
+    }
+
+    /// Request a running Lua code to stop.
+    ///
+    /// The running call or resume will fails with `interrupted` error. If there are no Lua code
+    /// currently running the next one will be interrupted instead.
+    #[inline(always)]
+    pub fn interrupt(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,55 @@
+use crate::PositiveInt;
+use crate::ffi::{LUA_MASKCALL, LUA_MASKCOUNT, LUA_MASKLINE, LUA_MASKRET};
+use std::ffi::c_int;
+use std::ops::BitOr;
+
+/// Set of events to trigger a hook.
+///
+/// Use `|` to combine multiple events.
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub struct HookMask {
+    mask: c_int,
+    count: c_int,
+}
+
+impl HookMask {
+    /// Trigger when the interpreter calls a function, including a tail call.
+    pub const CALL: Self = Self::new(LUA_MASKCALL, 0);
+    /// Trigger when the interpreter is about to return from a function.
+    pub const RET: Self = Self::new(LUA_MASKRET, 0);
+    /// Trigger when the interpreter is about to start the execution of a new line of code.
+    pub const LINE: Self = Self::new(LUA_MASKLINE, 0);
+
+    /// Trigger after the interpreter executes every `n` instructions.
+    pub const fn count(n: PositiveInt) -> Self {
+        Self::new(LUA_MASKCOUNT, n.get())
+    }
+
+    const fn new(mask: c_int, count: c_int) -> Self {
+        Self { mask, count }
+    }
+
+    pub(crate) const fn bits(self) -> c_int {
+        self.mask
+    }
+
+    pub(crate) const fn interval(self) -> c_int {
+        self.count
+    }
+}
+
+impl BitOr for HookMask {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self {
+            mask: self.mask | rhs.mask,
+            count: if rhs.count != 0 {
+                rhs.count
+            } else {
+                self.count
+            },
+        }
+    }
+}
@@ -0,0 +1,298 @@
+pub use self::context::*;
+pub use self::interrupt::*;
+pub use self::mask::*;
+
+use crate::ffi::{
+    LUA_HOOKTAILCALL, LUA_MASKCALL, lua_Debug, lua_State, zl_getextraspace, zl_pushlstring,
+    zl_raise, zl_sethook, zl_yield,
+};
+use crate::state::ExtraData;
+use crate::{Error, ErrorKind, PositiveInt};
+use std::borrow::Cow;
+use std::sync::atomic::Ordering;
+
+mod context;
+mod interrupt;
+mod mask;
+
+/// Type of Rust function to use as a Lua hook.
+pub type HookFn = dyn FnMut(&mut HookContext) -> Result<(), Error>;
+
+/// Number of instructions to check for interruption when the user does not specify a count.
+const INTERRUPT_COUNT: PositiveInt = PositiveInt::new(1000).unwrap();
+
+/// Install [`hook()`] to `state` according to `ex`.
+///
+/// # Safety
+/// `ex` must be the one associated with `state`.
+pub(crate) unsafe fn install(state: *mut lua_State, ex: &ExtraData) {
+    let mut mask = ex.hook_mask.get();
+
+    if ex.interrupt.get().is_some() && mask.interval() == 0 {
+        mask = mask | HookMask::count(INTERRUPT_COUNT);
+    }
+
+    match mask.bits() {
+        0 => unsafe { zl_sethook(state, None, 0, 0) },
+        v => unsafe { zl_sethook(state, Some(hook), v, mask.interval()) },
+    }
+}
+
+/// Clear interruption request when a host-level call or resume on `state` return.
+///
+/// # Safety
+/// `ex` must be the one associated with `state`.
+pub(crate) unsafe fn clear_interrupt(state: *mut lua_State, ex: &ExtraData) {
+    if ex
+        .interrupt
+        .get()
+        .is_some_and(|v| v.swap(false, Ordering::Relaxed))
+    {
+        unsafe { install(state, ex) };
+    }
+}
+
+/// Implementation of `lua_Hook` that Zero Lua install to `lua_State`.
+unsafe extern "C-unwind" fn hook(#[allow(non_snake_case)] L: *mut lua_State, ar: *mut lua_Debug) {
+    let ex = unsafe { &*zl_getextraspace(L).cast::<*const ExtraData>().read() };
+
+    // Check if interrupted. We don't clear the flag here otherwise the error can be caught by
+    // pcall and the code continue running. It will be cleared when the host-level call return. We
+    // also need to trigger the hook on every instruction from now on since the instruction count
+    // is shared with the protected call that catch the error.
+    if ex
+        .interrupt
+        .get()
+        .is_some_and(|v| v.load(Ordering::Relaxed))
+    {
+        let mask = ex.hook_mask.get() | HookMask::count(PositiveInt::ONE);
+
+        unsafe { zl_sethook(L, Some(hook), mask.bits(), mask.interval()) };
+        unsafe { raise(L, b"interrupted\0".into()) };
+    }
+
+    // Check if the user interested in this event. The count event may come from interruption.
+    let event = match unsafe { (*ar).event } {
+        LUA_HOOKTAILCALL => LUA_MASKCALL,
+        v => 1 << v,
+    };
+
+    if ex.hook_mask.get().bits() & event == 0 {
+        return;
+    }
+
+    // Invoke user hook. The hook will not be re-entered since Lua does not call a hook while
+    // running a hook.
+    let mut h = match ex.hook.try_borrow_mut() {
+        Ok(v) => v,
+        Err(_) => return,
+    };
+
+    let f = match h.as_mut() {
+        Some(v) => v,
+        None => return,
+    };
+
+    let mut cx = unsafe { HookContext::new(L, ar) };
+    let r = f(&mut cx);
+
+    drop(h);
+
+    if let Err(e) = r {
+        unsafe { raise(L, message(e)) };
+    }
+
+    if cx.take_yield() {
+        unsafe { zl_yield(L, 0) };
+    }
+}
+
+fn message(e: Error) -> Cow<'static, [u8]> {
+    // All messages are null-terminated.
+    let (n, m, s) = match e.into() {
+        ErrorKind::Arg(n, m) => (n, m, ""),
+        ErrorKind::ArgType(n, m) => (n, m, " expected"),
+        ErrorKind::Other(m) => return m,
+        ErrorKind::WithLevel(_, m) => return m,
+    };
+
+    let m = String::from_utf8_lossy(&m[..(m.len() - 1)]);
+
+    format!("bad argument #{} ({m}{s})\0", n.get())
+        .into_bytes()
+        .into()
+}
+
+/// # Safety
+/// `m` must be null-terminated.
+unsafe fn raise(#[allow(non_snake_case)] L: *mut lua_State, m: Cow<[u8]>) -> ! {
+    // We need to copy the message to Lua first since the raise will not run Rust destructors.
+    unsafe { zl_pushlstring(L, m.as_ptr().cast(), m.len() - 1) };
+    drop(m);
+    unsafe { zl_raise(L) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Async, ChunkType, Frame, Lua};
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    #[test]
+    fn interrupt() {
+        let mut lua = Lua::new(None).unwrap();
+        let h = lua.interrupt_handle();
+
+        h.interrupt();
+
+        let f = lua
+            .load(None, ChunkType::Text, "while true do end")
+            .unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("unexpected success"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "interrupted");
+        drop(e);
+
+        // The flag should be cleared.
+        let f = lua.load(None, ChunkType::Text, "return 1").unwrap();
+
+        assert!(f.call().is_ok());
+
+        // The error should not be able to catch by pcall.
+        lua.require_base();
+        h.interrupt();
+
+        let f = lua
+            .load(
+                None,
+                ChunkType::Text,
+                "while true do pcall(function() while true do end end) end",
+            )
+            .unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("unexpected success"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "interrupted");
+        drop(e);
+
+        let f = lua.load(None, ChunkType::Text, "return 1").unwrap();
+
+        assert!(f.call().is_ok());
+    }
+
+    #[test]
+    fn line_hook() {
+        let mut lua = Lua::new(None).unwrap();
+        let lines = Rc::new(RefCell::new(Vec::new()));
+        let hook = lines.clone();
+
+        lua.set_hook(HookMask::LINE, move |cx| {
+            assert_eq!(cx.event(), HookEvent::Line);
+
+            hook.borrow_mut().push(cx.current_line().unwrap());
+
+            Ok(())
+        });
+
+        let f = lua
+            .load(
+                Some(c"=test"),
+                ChunkType::Text,
+                "local a = 1\nlocal b = 2\n",
+            )
+            .unwrap();
+
+        assert!(f.call().is_ok());
+        assert_eq!(*lines.borrow(), [1, 2]);
+    }
+
+    #[test]
+    fn error() {
+        let mut lua = Lua::new(None).unwrap();
+
+        lua.set_hook(HookMask::LINE, |cx| match cx.current_line() {
+            Some(2) => Err(Error::other(c"line 2 is not allowed")),
+            _ => Ok(()),
+        });
+
+        let f = lua
+            .load(Some(c"=test"), ChunkType::Text, "local a = 1\nlocal b = 2")
+            .unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("unexpected success"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "line 2 is not allowed");
+    }
+
+    #[test]
+    fn request_yield() {
+        let lua = Lua::new(None).unwrap().into_async();
+        let results = Rc::new(RefCell::new(Vec::new()));
+        let r = results.clone();
+
+        lua.set_hook(HookMask::LINE, move |cx| {
+            r.borrow_mut().push(cx.request_yield());
+            Ok(())
+        });
+
+        // Yield on every line.
+        let mut th = lua.spawn();
+        let chunk = "local a = 1\nlocal b = 2\nreturn a + b";
+        let f = th.load(None, ChunkType::Text, chunk).unwrap();
+        let mut f = f.into_async();
+        let mut yields = 0;
+
+        pollster::block_on(async {
+            loop {
+                match f.resume().await.ok().unwrap() {
+                    Async::Yield(r) => assert!(r.is_empty()),
+                    Async::Finish(mut r) => {
+                        assert_eq!(r.to_int(1), Some(3));
+                        break;
+                    }
+                }
+
+                yields += 1;
+            }
+        });
+
+        assert_eq!(yields, 3);
+        assert_eq!(*results.borrow(), [true, true, true]);
+    }
+
+    #[test]
+    fn request_yield_coroutine() {
+        let mut lua = Lua::new(None).unwrap();
+        let requested = Rc::new(Cell::new(false));
+        let r = requested.clone();
+
+        lua.require_base();
+        lua.require_coroutine(true);
+        lua.set_hook(HookMask::LINE, move |cx| {
+            r.set(r.get() | cx.request_yield());
+            Ok(())
+        });
+
+        // The hook should not yield the coroutine of the script.
+        let chunk = "local f = coroutine.wrap(function()\n\
+            local a = 1\n\
+            coroutine.yield(a)\n\
+            return 2\n\
+            end)\n\
+            return f(), f()";
+        let f = lua.load(None, ChunkType::Text, chunk).unwrap();
+        let mut r = f.call().ok().unwrap();
+
+        assert_eq!(r.to_int(1), Some(1));
+        assert_eq!(r.to_int(2), Some(2));
+        assert!(!requested.get());
+    }
+}
@@ -1,26 +1,42 @@
 #![doc = include_str!("../README.md")]
 
 pub use self::boolean::*;
+pub use self::cache::*;
+pub use self::clock::*;
 pub use self::context::*;
 pub use self::convert::*;
+pub use self::coroutine::*;
+pub use self::debug::*;
 pub use self::error::*;
 pub use self::frame::*;
 pub use self::function::*;
+pub use self::gc::*;
 pub use self::global::*;
+pub use self::hook::*;
 pub use self::iter::*;
+pub use self::lightud::*;
 pub use self::module::*;
 pub use self::nil::*;
+pub use self::number::*;
 pub use self::option::*;
+pub use self::os::*;
+pub use self::package::*;
+pub use self::reader::*;
+pub use self::snippet::*;
 pub use self::string::*;
+pub use self::syntax::*;
 pub use self::table::*;
 pub use self::thread::*;
+pub use self::transform::*;
 pub use self::ty::*;
 pub use self::unknown::*;
 pub use self::userdata::*;
 pub use self::util::*;
+pub use self::vfs::*;
+pub use self::warn::*;
 pub use zl_macros::*;
 
-use self::ffi::{zl_getiuservalue, zl_getmetafield, zl_pop, zl_tolstring};
+use self::ffi::{zl_getiuservalue, zl_getmetafield, zl_pop, zl_pushlstring, zl_tolstring};
 use self::state::RawState;
 use std::borrow::Cow;
 use std::ffi::{CStr, c_int};
@@ -28,31 +44,51 @@ use std::mem::transmute;
 use std::ptr::null_mut;
 
 mod boolean;
+mod cache;
+mod clock;
 mod context;
 mod convert;
+mod coroutine;
+mod debug;
 mod error;
 mod ffi;
+mod file;
 mod frame;
 mod function;
+mod gc;
 mod global;
+mod hook;
 mod iter;
+mod lightud;
 mod module;
 mod nil;
+mod number;
 mod option;
+mod os;
+mod package;
+mod reader;
+mod snippet;
 mod state;
 mod string;
+mod syntax;
 mod table;
 mod thread;
+mod transform;
 mod ty;
 mod unknown;
 mod userdata;
 mod util;
+mod vfs;
+mod warn;
 
 extern crate zl_sys; // Required since no Rust code references this crate.
 
 pub type PanicHandler = dyn Fn(Option<&str>);
 
 /// Allowed chunk type to load.
+///
+/// Binary chunk will be rejected unless it was enabled with [`Lua::allow_binary()`] since malformed
+/// bytecode can crash Lua.
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum ChunkType {
     Text,
@@ -61,25 +97,74 @@ pub enum ChunkType {
 }
 
 impl ChunkType {
-    const fn to_c_str(self) -> &'static CStr {
-        match self {
-            Self::Text => c"t",
-            Self::Binary => c"b",
-            Self::Both => c"bt",
+    /// Returns [`None`] if this is [`ChunkType::Binary`] and `binary` is `false`.
+    const fn to_c_str(self, binary: bool) -> Option<&'static CStr> {
+        match (self, binary) {
+            (Self::Text, _) | (Self::Both, false) => Some(c"t"),
+            (Self::Binary, true) => Some(c"b"),
+            (Self::Binary, false) => None,
+            (Self::Both, true) => Some(c"bt"),
         }
     }
 }
 
+/// Error message when loading [`ChunkType::Binary`] without [`Lua::allow_binary()`].
+const BINARY_DENIED: &str = "loading a binary chunk is not allowed";
+
+/// Push [`BINARY_DENIED`] to `p` as an error.
+fn binary_denied<P: Frame>(p: &mut P) -> Str<'_, P> {
+    unsafe {
+        zl_pushlstring(
+            p.state(),
+            BINARY_DENIED.as_ptr().cast(),
+            BINARY_DENIED.len(),
+        )
+    };
+    unsafe { Str::new(p) }
+}
+
+/// Lua source embedded with [`include_lua!`].
+///
+/// The chunk was checked for syntax errors at compile time. Pass it to [`Frame::load()`] with
+/// [`LuaSource::name()`] as the chunk name.
+#[derive(Clone, Copy)]
+pub struct LuaSource {
+    name: &'static CStr,
+    code: &'static [u8],
+}
+
+impl LuaSource {
+    #[doc(hidden)]
+    pub const fn new(name: &'static CStr, code: &'static [u8]) -> Self {
+        Self { name, code }
+    }
+
+    /// Returns chunk name in the form of `@path` where `path` is the path given to
+    /// [`include_lua!`].
+    pub const fn name(&self) -> &'static CStr {
+        self.name
+    }
+}
+
+impl AsRef<[u8]> for LuaSource {
+    fn as_ref(&self) -> &[u8] {
+        self.code
+    }
+}
+
 /// Encapsulates a value in the stack.
 #[non_exhaustive]
 #[repr(i32)]
 pub enum Value<'a, P: Frame> {
     Nil(Nil<'a, P>) = 0,
     Boolean(Bool<'a, P>) = 1,
+    LightUserData(LightUserData<'a, P>) = 2,
+    Number(Num<'a, P>) = 3,
     String(Str<'a, P>) = 4,
     Table(Table<'a, P>) = 5,
     Function(Function<'a, P>) = 6,
     UserData(UserData<'a, P>) = 7,
+    Thread(Coroutine<'a, P>) = 8,
 }
 
 impl<'a, P: Frame> Value<'a, P> {
@@ -118,39 +203,38 @@ impl<'a, P: Frame> Value<'a, P> {
 
     #[inline(always)]
     pub(crate) unsafe fn from_table<K: TableGetter>(p: &'a mut P, t: c_int, k: K) -> Self {
-        match unsafe { k.get_value(p.state(), t) } {
-            Type::None => unreachable!(),
-            Type::Nil => Self::Nil(unsafe { Nil::new(p) }),
-            Type::Boolean => Self::Boolean(unsafe { Bool::new(p) }),
-            Type::LightUserData => todo!(),
-            Type::Number => todo!(),
-            Type::String => Self::String(unsafe { Str::new(p) }),
-            Type::Table => Self::Table(unsafe { Table::new(p) }),
-            Type::Function => Self::Function(unsafe { Function::new(p) }),
-            Type::UserData => Self::UserData(unsafe { UserData::new(p) }),
-            Type::Thread => todo!(),
-        }
+        let ty = unsafe { k.get_value(p.state(), t) };
+
+        unsafe { Self::from_top(p, ty) }
     }
 
     #[inline(always)]
     pub(crate) unsafe fn from_uv(p: &'a mut P, d: c_int, v: u16) -> Option<Self> {
-        let v = match unsafe { zl_getiuservalue(p.state(), d, v) } {
+        match unsafe { zl_getiuservalue(p.state(), d, v) } {
             Type::None => {
                 unsafe { zl_pop(p.state(), 1) };
-                return None;
+                None
             }
-            Type::Nil => Value::Nil(unsafe { Nil::new(p) }),
-            Type::Boolean => Value::Boolean(unsafe { Bool::new(p) }),
-            Type::LightUserData => todo!(),
-            Type::Number => todo!(),
-            Type::String => Value::String(unsafe { Str::new(p) }),
-            Type::Table => Value::Table(unsafe { Table::new(p) }),
-            Type::Function => Value::Function(unsafe { Function::new(p) }),
-            Type::UserData => Value::UserData(unsafe { UserData::new(p) }),
-            Type::Thread => todo!(),
-        };
+            v => Some(unsafe { Self::from_top(p, v) }),
+        }
+    }
 
-        Some(v)
+    /// # Safety
+    /// `ty` must be the type of the value on the top of stack and it must not be [`Type::None`].
+    #[inline(always)]
+    pub(crate) unsafe fn from_top(p: &'a mut P, ty: Type) -> Self {
+        match ty {
+            Type::None => unreachable!(),
+            Type::Nil => Self::Nil(unsafe { Nil::new(p) }),
+            Type::Boolean => Self::Boolean(unsafe { Bool::new(p) }),
+            Type::LightUserData => Self::LightUserData(unsafe { LightUserData::new(p) }),
+            Type::Number => Self::Number(unsafe { Num::new(p) }),
+            Type::String => Self::String(unsafe { Str::new(p) }),
+            Type::Table => Self::Table(unsafe { Table::new(p) }),
+            Type::Function => Self::Function(unsafe { Function::new(p) }),
+            Type::UserData => Self::UserData(unsafe { UserData::new(p) }),
+            Type::Thread => Self::Thread(unsafe { Coroutine::new(p) }),
+        }
     }
 }
 
@@ -1,9 +1,18 @@
-use crate::Frame;
-use crate::ffi::{lua_State, zl_pop, zl_pushvalue, zl_replace, zl_setfield, zl_setglobal};
+use crate::ffi::{
+    lua_State, zl_getfield, zl_gettop, zl_istable, zl_pop, zl_pushvalue, zl_replace, zl_setfield,
+    zl_setmodule,
+};
 use crate::state::RawState;
-use std::ffi::{CStr, c_int};
+use crate::{Frame, Type};
+use std::ffi::{CStr, CString, c_int};
 
 /// Struct to build Lua module.
+///
+/// A dotted name like `game.physics` will create a table for each level that does not exists and
+/// register it to `package.loaded`. Nothing will be registered if no value has been pushed.
+///
+/// Zero Lua also provides [module](zl_macros::module()) attribute to generate a module from an
+/// `impl` block or an inline `mod`.
 pub struct ModuleBuilder<'p, P, N>
 where
     P: Frame,
@@ -11,6 +20,10 @@ where
 {
     parent: &'p mut P,
     name: N,
+    global: bool,
+    loaded: c_int,
+    owner: c_int,
+    base: c_int,
     has_value: bool,
 }
 
@@ -20,15 +33,72 @@ where
     N: AsRef<CStr>,
 {
     /// # Safety
-    /// Top of the stack must be a module table.
+    /// Top of the stack must be `package.loaded`.
     #[inline(always)]
-    pub(crate) unsafe fn new(parent: &'p mut P, name: N) -> Self {
+    pub(crate) unsafe fn new(parent: &'p mut P, name: N, global: bool) -> Self {
+        let loaded = unsafe { zl_gettop(parent.state()) };
+
         Self {
             parent,
             name,
+            global,
+            loaded,
+            owner: 0,
+            base: 1,
             has_value: false,
         }
     }
+
+    /// Returns [`None`] if the submodule with the same name already loaded.
+    ///
+    /// This will push an empty table as the module if no value has been pushed yet.
+    ///
+    /// # Panics
+    /// If the module is not a table or `name` contains a dot.
+    pub fn submodule(&mut self, name: &CStr) -> Option<ModuleBuilder<Self, CString>> {
+        if name.to_bytes().contains(&b'.') {
+            panic!("submodule name cannot contains a dot");
+        }
+
+        if !self.has_value {
+            self.push_table(0, 0);
+        }
+
+        if unsafe { !zl_istable(self.state(), -1) } {
+            panic!("the module is not a table");
+        }
+
+        // Check if already loaded.
+        let mut full = self.name.as_ref().to_bytes().to_vec();
+
+        full.push(b'.');
+        full.extend_from_slice(name.to_bytes());
+
+        let full = CString::new(full).unwrap();
+        let loaded = self.loaded;
+
+        match unsafe { zl_getfield(self.state(), loaded, full.as_ptr()) } {
+            Type::None => unreachable!(),
+            Type::Nil => unsafe { zl_pop(self.state(), 1) },
+            _ => {
+                unsafe { zl_pop(self.state(), 1) };
+                return None;
+            }
+        }
+
+        // Create builder.
+        let owner = unsafe { zl_gettop(self.state()) };
+
+        Some(ModuleBuilder {
+            parent: self,
+            name: full,
+            global: false,
+            loaded,
+            owner,
+            base: 0,
+            has_value: false,
+        })
+    }
 }
 
 impl<P, N> Drop for ModuleBuilder<'_, P, N>
@@ -38,15 +108,26 @@ where
 {
     #[inline(always)]
     fn drop(&mut self) {
+        let s = self.parent.state();
+
         if self.has_value {
-            let name = self.name.as_ref().as_ptr();
+            let name = self.name.as_ref();
+
+            if self.owner != 0 {
+                let i = name.to_bytes().iter().rposition(|&b| b == b'.').unwrap();
+                let key = unsafe { name.as_ptr().add(i + 1) };
 
-            unsafe { zl_pushvalue(self.parent.state(), -1) };
-            unsafe { zl_setfield(self.parent.state(), -3, name) };
-            unsafe { zl_setglobal(self.parent.state(), name) };
+                unsafe { zl_pushvalue(s, -1) };
+                unsafe { zl_setfield(s, self.loaded, name.as_ptr()) };
+                unsafe { zl_setfield(s, self.owner, key) };
+            } else {
+                unsafe { zl_setmodule(s, self.loaded, name.as_ptr(), self.global) };
+            }
         }
 
-        unsafe { zl_pop(self.parent.state(), 1) };
+        if self.base != 0 {
+            unsafe { zl_pop(s, self.base) };
+        }
     }
 }
 
@@ -75,3 +156,88 @@ where
         self.has_value = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{ChunkType, Frame, Lua};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn lazy() {
+        let mut lua = Lua::new(None).unwrap();
+        let loaded = Rc::new(Cell::new(0));
+        let l = loaded.clone();
+
+        lua.require_base();
+        lua.require_package(false, false);
+
+        assert!(lua.register_lazy_module(c"foo", move |cx| {
+            l.set(l.get() + 1);
+            cx.push_str("bar");
+            Ok(())
+        }));
+
+        assert!(!lua.register_lazy_module(c"foo", |_| Ok(())));
+        assert_eq!(loaded.get(), 0);
+
+        let chunk = "local a = require('foo')\n\
+            local b = require('foo')\n\
+            error(a .. b .. tostring(foo), 0)";
+        let f = lua.load(Some(c"=test"), ChunkType::Text, chunk).unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("unexpected success"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "barbarnil");
+        drop(e);
+
+        assert_eq!(loaded.get(), 1);
+    }
+
+    #[test]
+    fn dotted() {
+        let mut lua = Lua::new(None).unwrap();
+
+        lua.require_base();
+        lua.require_package(true, false);
+
+        // Build game.core and game.core.physics.
+        let mut m = lua.register_module(c"game.core", true).unwrap();
+        let mut s = m.submodule(c"physics").unwrap();
+
+        s.push_table(0, 1).set(c"g").push_str("9.8");
+        drop(s);
+
+        assert!(m.submodule(c"physics").is_none());
+        drop(m);
+
+        let chunk = "local p = require('game.core.physics')\n\
+            assert(package.loaded['game.core'].physics == p)\n\
+            assert(package.loaded.game == game)\n\
+            error(game.core.physics.g, 0)";
+        let f = lua.load(Some(c"=test"), ChunkType::Text, chunk).unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("unexpected success"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "9.8");
+        drop(e);
+
+        // Nothing should be registered without a value.
+        drop(lua.register_module(c"empty.core", true).unwrap());
+
+        // Parent that is not a table.
+        let chunk = "assert(package.loaded.empty == nil)\n\
+            assert(package.loaded['empty.core'] == nil)\n\
+            assert(empty == nil)\n\
+            package.loaded.scalar = 1";
+        let f = lua.load(Some(c"=test"), ChunkType::Text, chunk).unwrap();
+
+        assert!(f.call().is_ok());
+        assert!(lua.register_module(c"scalar.core", true).is_none());
+        assert!(lua.register_module(c"scalar", true).is_none());
+    }
+}
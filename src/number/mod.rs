@@ -0,0 +1,71 @@
+use crate::ffi::{lua_State, zl_isinteger, zl_pop, zl_tointegerx, zl_tonumberx};
+use crate::state::RawState;
+use crate::{Frame, Unknown};
+use std::ffi::c_int;
+use std::mem::ManuallyDrop;
+use std::ops::DerefMut;
+use std::ptr::null_mut;
+
+/// Represents a number on the top of stack.
+pub struct Num<'p, P: Frame>(&'p mut P);
+
+impl<'p, P: Frame> Num<'p, P> {
+    /// # Safety
+    /// Top of the stack must be a number.
+    #[inline(always)]
+    pub(crate) unsafe fn new(p: &'p mut P) -> Self {
+        Self(p)
+    }
+
+    /// Returns `true` if this number is an integer (not a float).
+    #[inline(always)]
+    pub fn is_int(&mut self) -> bool {
+        unsafe { zl_isinteger(self.0.state(), -1) }
+    }
+
+    /// Returns [`None`] if this number is a float that does not have an exact representation in
+    /// integer.
+    #[inline(always)]
+    pub fn to_int(&mut self) -> Option<i64> {
+        let mut ok = 0;
+        let val = unsafe { zl_tointegerx(self.0.state(), -1, &mut ok) };
+
+        if ok == 0 { None } else { Some(val) }
+    }
+
+    #[inline(always)]
+    pub fn to_float(&mut self) -> f64 {
+        unsafe { zl_tonumberx(self.0.state(), -1, null_mut()) }
+    }
+
+    #[inline(always)]
+    pub fn into_unknown(self) -> Unknown<'p, P> {
+        unsafe { Unknown::new(ManuallyDrop::new(self).deref_mut().0) }
+    }
+}
+
+impl<P: Frame> Drop for Num<'_, P> {
+    #[inline(always)]
+    fn drop(&mut self) {
+        unsafe { self.0.release_values(1) };
+    }
+}
+
+impl<P: Frame> RawState for Num<'_, P> {
+    #[inline(always)]
+    fn state(&mut self) -> *mut lua_State {
+        self.0.state()
+    }
+
+    #[inline(always)]
+    unsafe fn release_values(&mut self, n: c_int) {
+        unsafe { zl_pop(self.state(), n) };
+    }
+}
+
+impl<'p, P: Frame> From<Num<'p, P>> for Unknown<'p, P> {
+    #[inline(always)]
+    fn from(value: Num<'p, P>) -> Self {
+        value.into_unknown()
+    }
+}
@@ -0,0 +1,319 @@
+use crate::ffi::{
+    lua_State, zl_pop, zl_pushinteger, zl_pushlightuserdata, zl_pushlstring, zl_raise,
+    zl_toboolean, zl_tointegerx, zl_touserdata, zl_type,
+};
+use crate::state::{ExtraData, RawState};
+use crate::{Context, Error, Frame, PositiveInt, Table, Type};
+use std::ffi::c_int;
+
+/// Options for [`Frame::require_safe_os()`].
+///
+/// All functions are denied by default.
+#[derive(Default)]
+pub struct SafeOs {
+    execute: OsAccess<ExecuteFn>,
+    remove: OsAccess<RemoveFn>,
+    rename: OsAccess<RenameFn>,
+    tmpname: OsAccess<TmpnameFn>,
+    getenv: OsAccess<GetenvFn>,
+}
+
+impl SafeOs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set access of `os.execute`.
+    ///
+    /// The redirected function receive the command and returns its exit code. `os.execute` without
+    /// a command will return `true` in this case.
+    pub fn execute(mut self, v: OsAccess<ExecuteFn>) -> Self {
+        self.execute = v;
+        self
+    }
+
+    pub fn remove(mut self, v: OsAccess<RemoveFn>) -> Self {
+        self.remove = v;
+        self
+    }
+
+    pub fn rename(mut self, v: OsAccess<RenameFn>) -> Self {
+        self.rename = v;
+        self
+    }
+
+    pub fn tmpname(mut self, v: OsAccess<TmpnameFn>) -> Self {
+        self.tmpname = v;
+        self
+    }
+
+    pub fn getenv(mut self, v: OsAccess<GetenvFn>) -> Self {
+        self.getenv = v;
+        self
+    }
+
+    pub(crate) fn install<P: Frame>(self, t: &mut Table<P>) {
+        // Lua does not have a way to prevent os.exit from being caught by pcall so the best we can
+        // do is remember the code and raise a unique error object.
+        t.set(c"exit").push_fn(exit);
+
+        match self.execute {
+            OsAccess::Allow => {}
+            OsAccess::Deny => drop(t.set(c"execute").push_fn(|_| Err(deny("execute")))),
+            OsAccess::Redirect(f) => drop(t.set(c"execute").push_fn(move |cx| execute(cx, &f))),
+        }
+
+        match self.remove {
+            OsAccess::Allow => {}
+            OsAccess::Deny => drop(t.set(c"remove").push_fn(|_| Err(deny("remove")))),
+            OsAccess::Redirect(f) => drop(t.set(c"remove").push_fn(move |cx| {
+                let path = cx.to_str(PositiveInt::ONE);
+
+                result(cx, f(path).map_err(|e| format!("{path}: {e}")));
+
+                Ok(())
+            })),
+        }
+
+        match self.rename {
+            OsAccess::Allow => {}
+            OsAccess::Deny => drop(t.set(c"rename").push_fn(|_| Err(deny("rename")))),
+            OsAccess::Redirect(f) => drop(t.set(c"rename").push_fn(move |cx| {
+                let from = cx.to_str(PositiveInt::ONE);
+                let to = cx.to_str(PositiveInt::TWO);
+
+                result(cx, f(from, to).map_err(|e| format!("{from}: {e}")));
+
+                Ok(())
+            })),
+        }
+
+        match self.tmpname {
+            OsAccess::Allow => {}
+            OsAccess::Deny => drop(t.set(c"tmpname").push_fn(|_| Err(deny("tmpname")))),
+            OsAccess::Redirect(f) => drop(t.set(c"tmpname").push_fn(move |cx| {
+                let name =
+                    f().map_err(|e| Error::with_source("unable to generate a unique filename", e))?;
+
+                cx.push_str(name);
+
+                Ok(())
+            })),
+        }
+
+        match self.getenv {
+            OsAccess::Allow => {}
+            OsAccess::Deny => drop(t.set(c"getenv").push_fn(|_| Err(deny("getenv")))),
+            OsAccess::Redirect(f) => drop(t.set(c"getenv").push_fn(move |cx| {
+                match f(cx.to_str(PositiveInt::ONE)) {
+                    Some(v) => drop(cx.push_str(v)),
+                    None => drop(cx.push_nil()),
+                }
+
+                Ok(())
+            })),
+        }
+    }
+}
+
+/// Address of this variable is used as an error object raised by `os.exit`.
+static EXIT: u8 = 0;
+
+/// Access of a function in [`SafeOs`].
+#[derive(Default)]
+pub enum OsAccess<F: ?Sized> {
+    /// Use the implementation from Lua, which access the real system.
+    Allow,
+    /// Raise an error when the function is called.
+    #[default]
+    Deny,
+    /// Forward the call to a Rust function.
+    Redirect(Box<F>),
+}
+
+/// Type of Rust function to handle `os.execute`.
+pub type ExecuteFn = dyn Fn(&str) -> std::io::Result<c_int>;
+
+/// Type of Rust function to handle `os.remove`.
+pub type RemoveFn = dyn Fn(&str) -> std::io::Result<()>;
+
+/// Type of Rust function to handle `os.rename`.
+pub type RenameFn = dyn Fn(&str, &str) -> std::io::Result<()>;
+
+/// Type of Rust function to handle `os.tmpname`.
+pub type TmpnameFn = dyn Fn() -> std::io::Result<String>;
+
+/// Type of Rust function to handle `os.getenv`.
+pub type GetenvFn = dyn Fn(&str) -> Option<String>;
+
+fn exit(cx: &mut Context) -> Result<(), Error> {
+    let s = RawState::state(cx);
+    let code = match unsafe { zl_type(s, 1) } {
+        Type::None | Type::Nil => 0,
+        Type::Boolean => match unsafe { zl_toboolean(s, 1) } {
+            true => 0,
+            false => 1,
+        },
+        _ => {
+            let mut ok = 0;
+            let v = unsafe { zl_tointegerx(s, 1, &mut ok) };
+
+            match (ok, c_int::try_from(v)) {
+                (0, _) | (_, Err(_)) => {
+                    return Err(Error::arg_type(PositiveInt::ONE, c"integer"));
+                }
+                (_, Ok(v)) => v,
+            }
+        }
+    };
+
+    cx.extra1().exit.set(Some(code));
+
+    // The error object is a light userdata so the host can identify it even if the script catch
+    // it and raise it again.
+    unsafe { zl_pushlightuserdata(s, (&raw const EXIT).cast_mut().cast()) };
+    unsafe { zl_raise(s) };
+}
+
+/// Replace the error on the top of stack with a message if it was raised by `os.exit`. The exit
+/// code will be cleared if the error is something else.
+///
+/// # Safety
+/// Top of the stack must be an error object and `ex` must be the one associated with `L`.
+pub(crate) unsafe fn map_exit(#[allow(non_snake_case)] L: *mut lua_State, ex: &ExtraData) {
+    let code = match ex.exit.get() {
+        Some(v) => v,
+        None => return,
+    };
+
+    if unsafe { zl_touserdata(L, -1) }.cast_const() != &raw const EXIT {
+        ex.exit.set(None);
+        return;
+    }
+
+    let msg = format!("exit requested with code {code}");
+
+    unsafe { zl_pop(L, 1) };
+    unsafe { zl_pushlstring(L, msg.as_ptr().cast(), msg.len()) };
+}
+
+fn execute(cx: &mut Context, f: &ExecuteFn) -> Result<(), Error> {
+    let cmd = match cx.try_str(PositiveInt::ONE) {
+        Some(v) => v,
+        None => {
+            cx.push_bool(true);
+            return Ok(());
+        }
+    };
+
+    let code = match f(cmd) {
+        Ok(v) => v,
+        Err(e) => {
+            cx.push_nil();
+            cx.push_str(e.to_string());
+            return Ok(());
+        }
+    };
+
+    if code == 0 {
+        cx.push_bool(true);
+    } else {
+        cx.push_nil();
+    }
+
+    cx.push_str("exit");
+
+    unsafe { zl_pushinteger(RawState::state(cx), code.into()) };
+    unsafe { cx.release_values(1) };
+
+    Ok(())
+}
+
+fn result(cx: &mut Context, r: Result<(), String>) {
+    match r {
+        Ok(_) => drop(cx.push_bool(true)),
+        Err(e) => {
+            cx.push_nil();
+            cx.push_str(e);
+        }
+    }
+}
+
+fn deny(name: &str) -> Error {
+    Error::other(format!("'os.{name}' is not allowed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChunkType, Lua};
+
+    #[test]
+    fn safe() {
+        let mut lua = Lua::new(None).unwrap();
+        let opts = SafeOs::new()
+            .execute(OsAccess::Redirect(Box::new(|cmd| Ok(cmd.len() as c_int))))
+            .getenv(OsAccess::Redirect(Box::new(|n| {
+                (n == "FOO").then(|| "bar".into())
+            })));
+
+        lua.require_base();
+        lua.require_safe_os(true, opts);
+
+        // Check functions.
+        let chunk = "assert(os.execute() == true)\n\
+            assert(select(3, os.execute('abc')) == 3)\n\
+            assert(os.getenv('FOO') == 'bar')\n\
+            assert(os.getenv('BAR') == nil)\n\
+            assert(not pcall(os.remove, 'foo'))\n\
+            os.exit(false)";
+        let f = lua.load(None, ChunkType::Text, chunk).unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("os.exit should raise an error"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "exit requested with code 1");
+        drop(e);
+
+        assert_eq!(lua.take_exit_code(), Some(1));
+        assert_eq!(lua.take_exit_code(), None);
+
+        // Exit request that was caught and raised again.
+        let chunk = "local ok, e = pcall(os.exit, 3)\n\
+            assert(not ok)\n\
+            error(e)";
+        let f = lua.load(None, ChunkType::Text, chunk).unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("os.exit should raise an error"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "exit requested with code 3");
+        drop(e);
+
+        assert_eq!(lua.take_exit_code(), Some(3));
+
+        // Other error with the same message.
+        let chunk = "pcall(os.exit, 4)\n\
+            error('exit requested with code 4', 0)";
+        let f = lua.load(None, ChunkType::Text, chunk).unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("unexpected success"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "exit requested with code 4");
+        drop(e);
+
+        assert_eq!(lua.take_exit_code(), None);
+
+        // Exit request that was swallowed.
+        let f = lua
+            .load(None, ChunkType::Text, "pcall(os.exit, 5)")
+            .unwrap();
+
+        assert!(f.call().is_ok());
+        assert_eq!(lua.take_exit_code(), None);
+    }
+}
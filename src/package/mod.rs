@@ -0,0 +1,87 @@
+use crate::cache::load_cached;
+use crate::{ChunkType, Context, Error, Frame, PositiveInt};
+use std::borrow::Cow;
+use std::ffi::CString;
+
+/// Provides Lua modules to `require` from Rust.
+///
+/// Use [`Frame::add_searcher()`] to install the implementation into `package.searchers`.
+pub trait Searcher: 'static {
+    /// Returns chunk name and the content of module `name` or [`None`] if this searcher does not
+    /// have the module.
+    ///
+    /// The content will be loaded as [`ChunkType::Text`]. The compiled chunk will be cached if a
+    /// cache has been set with [`Lua::set_chunk_cache()`](crate::Lua::set_chunk_cache()).
+    fn search(&self, name: &str) -> Option<(CString, Cow<'static, [u8]>)>;
+}
+
+pub(crate) fn search<T: Searcher>(cx: &mut Context, s: &T) -> Result<(), Error> {
+    // Non UTF-8 name never be found.
+    let name = match std::str::from_utf8(cx.to_bytes(PositiveInt::ONE)) {
+        Ok(v) => v,
+        Err(_) => return Ok(()),
+    };
+
+    // Search.
+    let (chunk, data) = match s.search(name) {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+
+    // Load.
+    match load_cached(cx, &chunk, None, ChunkType::Text, &data) {
+        Ok(f) => drop(f),
+        Err(mut e) => {
+            let m = format!(
+                "error loading module '{}' from '{}':\n\t{}",
+                name,
+                chunk.to_string_lossy(),
+                String::from_utf8_lossy(e.to_bytes())
+            );
+
+            return Err(Error::other(m));
+        }
+    }
+
+    // Pass chunk name to the loader the same as Lua searchers.
+    cx.push_str(chunk.to_bytes());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lua;
+
+    struct Memory;
+
+    impl Searcher for Memory {
+        fn search(&self, name: &str) -> Option<(CString, Cow<'static, [u8]>)> {
+            match name {
+                "foo" => Some((c"=foo".into(), Cow::Borrowed(b"return { x = ... }"))),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn searcher() {
+        let mut lua = Lua::new(None).unwrap();
+
+        lua.require_base();
+        lua.require_package(true, false);
+        lua.add_searcher(Memory);
+
+        let chunk = "local m = require('foo')\n\
+            local ok = pcall(require, 'bar')\n\
+            error(m.x .. ',' .. tostring(package.loadlib) .. ',' .. tostring(ok), 0)";
+        let f = lua.load(Some(c"=test"), ChunkType::Text, chunk).unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("unexpected success"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "foo,nil,false");
+    }
+}
@@ -0,0 +1,188 @@
+use crate::ffi::lua_State;
+use std::ffi::{c_char, c_void};
+use std::future::poll_fn;
+use std::io::{ErrorKind, Read};
+use std::pin::Pin;
+use std::task::Poll;
+
+/// Asynchronous version of [`Read`] for [`Frame::load_async_reader()`](crate::Frame::load_async_reader()).
+///
+/// This has the same shape as `AsyncRead` from `futures-io`. The orphan rule does not allow you
+/// to implement this trait on a type from the other crate so use [`PollRead`] to wrap it instead.
+pub trait AsyncRead {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>>;
+}
+
+impl AsyncRead for &[u8] {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _: &mut std::task::Context,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Poll::Ready(Read::read(&mut *self, buf))
+    }
+}
+
+impl<T: AsyncRead + Unpin + ?Sized> AsyncRead for &mut T {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut **self).poll_read(cx, buf)
+    }
+}
+
+/// Implementation of [`AsyncRead`] that forward the call to a closure.
+///
+/// This can be used with any asynchronous reader (e.g. `futures_io::AsyncRead`):
+///
+/// ```ignore
+/// let r = PollRead(|cx, buf| Pin::new(&mut file).poll_read(cx, buf));
+/// ```
+pub struct PollRead<F>(pub F)
+where
+    F: FnMut(&mut std::task::Context, &mut [u8]) -> Poll<std::io::Result<usize>>;
+
+impl<F> AsyncRead for PollRead<F>
+where
+    F: FnMut(&mut std::task::Context, &mut [u8]) -> Poll<std::io::Result<usize>>,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        (self.get_mut().0)(cx, buf)
+    }
+}
+
+// The closure is never pinned.
+impl<F> Unpin for PollRead<F> where
+    F: FnMut(&mut std::task::Context, &mut [u8]) -> Poll<std::io::Result<usize>>
+{
+}
+
+/// State of [`read()`].
+pub(crate) struct ChunkReader<R> {
+    src: R,
+    buf: Box<[u8]>,
+    pub error: Option<std::io::Error>,
+}
+
+impl<R: Read> ChunkReader<R> {
+    pub fn new(src: R) -> Self {
+        Self {
+            src,
+            buf: vec![0; 8192].into_boxed_slice(),
+            error: None,
+        }
+    }
+}
+
+/// Implementation of `lua_Reader`. `ud` must be a pointer to [`ChunkReader`].
+///
+/// On error this will store the error in [`ChunkReader::error`] and signal the end of the chunk to Lua.
+pub(crate) unsafe extern "C-unwind" fn read<R: Read>(
+    _: *mut lua_State,
+    ud: *mut c_void,
+    size: *mut usize,
+) -> *const c_char {
+    let r = unsafe { &mut *ud.cast::<ChunkReader<R>>() };
+
+    loop {
+        match r.src.read(&mut r.buf) {
+            Ok(n) => {
+                unsafe { size.write(n) };
+                return r.buf.as_ptr().cast();
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => {
+                r.error = Some(e);
+                unsafe { size.write(0) };
+                return std::ptr::null();
+            }
+        }
+    }
+}
+
+/// Read all data from `r`.
+pub(crate) async fn read_to_end<R: AsyncRead>(r: R) -> std::io::Result<Vec<u8>> {
+    let mut r = std::pin::pin!(r);
+    let mut data = Vec::new();
+    let mut buf = [0; 8192];
+
+    loop {
+        let n = match poll_fn(|cx| r.as_mut().poll_read(cx, &mut buf)).await {
+            Ok(0) => break,
+            Ok(v) => v,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        data.extend_from_slice(&buf[..n]);
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChunkType, Frame, Lua};
+
+    struct Failing;
+
+    impl Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(ErrorKind::BrokenPipe.into())
+        }
+    }
+
+    #[test]
+    fn load() {
+        let mut lua = Lua::new(None).unwrap();
+        let chunk = "return 1".to_owned() + &" + 1".repeat(5000);
+        let f = lua
+            .load_reader(None, ChunkType::Text, chunk.as_bytes())
+            .unwrap()
+            .unwrap();
+        let mut r = f.call().unwrap();
+
+        assert_eq!(r.to_int(1).unwrap(), 5001);
+        drop(r);
+
+        // Check errors.
+        let r = lua.load_reader(None, ChunkType::Text, b"return 1 +".as_slice());
+
+        assert!(matches!(r, Ok(Err(_))));
+        drop(r);
+
+        let r = lua.load_reader(None, ChunkType::Text, b"return 1".chain(Failing));
+
+        assert_eq!(r.err().unwrap().kind(), ErrorKind::BrokenPipe);
+
+        // Check async.
+        let r = pollster::block_on(lua.load_async_reader(
+            None,
+            ChunkType::Text,
+            b"return 3".as_slice(),
+        ));
+        let mut r = r.unwrap().unwrap().call().unwrap();
+
+        assert_eq!(r.to_int(1).unwrap(), 3);
+        drop(r);
+
+        // Check adapter.
+        let mut src = b"return 4".as_slice();
+        let r = PollRead(|cx, buf| Pin::new(&mut src).poll_read(cx, buf));
+        let r = pollster::block_on(lua.load_async_reader(None, ChunkType::Text, r));
+        let mut r = r.unwrap().unwrap().call().unwrap();
+
+        assert_eq!(r.to_int(1).unwrap(), 4);
+    }
+}
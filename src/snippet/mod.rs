@@ -0,0 +1,170 @@
+use crate::ffi::{
+    LUA_MULTRET, ZL_REGISTRYINDEX, zl_gettop, zl_load, zl_pcall, zl_pop, zl_pushvalue, zl_rawgetp,
+    zl_rawsetp, zl_tolstringmeta,
+};
+use crate::hook::clear_interrupt;
+use crate::os::map_exit;
+use crate::transform::map_error;
+use crate::{Frame, FromLua, Function, IntoLua, Ret, Type};
+use std::ffi::CStr;
+use std::fmt::{Display, Formatter};
+
+/// Error from [`lua!`](crate::lua!).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnippetError {
+    /// The snippet raised an error. This contains the error message.
+    Run(String),
+    /// The results cannot be converted to the requested type.
+    Result,
+}
+
+impl std::error::Error for SnippetError {}
+
+impl Display for SnippetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Run(m) => f.write_str(m),
+            Self::Result => f.write_str("unexpected results from the snippet"),
+        }
+    }
+}
+
+/// Implementation of [`lua!`](crate::lua!).
+///
+/// The compiled chunk is stored in the registry with the address of `site` as a key.
+#[doc(hidden)]
+pub fn run_snippet<P, T, F>(
+    p: &mut P,
+    site: &'static u8,
+    name: &CStr,
+    code: &[u8],
+    args: F,
+) -> Result<T, SnippetError>
+where
+    P: Frame,
+    T: FromLua,
+    F: FnOnce(&mut Function<P>),
+{
+    let base = unsafe { zl_gettop(p.state()) };
+    let key = (site as *const u8).cast();
+
+    // Get the compiled chunk.
+    if unsafe { zl_rawgetp(p.state(), ZL_REGISTRYINDEX, key) } != Type::Function {
+        unsafe { zl_pop(p.state(), 1) };
+
+        if !unsafe {
+            zl_load(
+                p.state(),
+                name.as_ptr(),
+                code.as_ptr().cast(),
+                code.len(),
+                c"t".as_ptr(),
+            )
+        } {
+            return Err(unsafe { pop_error(p) });
+        }
+
+        unsafe { zl_pushvalue(p.state(), -1) };
+        unsafe { zl_rawsetp(p.state(), ZL_REGISTRYINDEX, key) };
+    }
+
+    // Push captured variables.
+    let mut f = unsafe { Function::new(p) };
+
+    args(&mut f);
+
+    std::mem::forget(f);
+
+    // Call.
+    let n = unsafe { zl_gettop(p.state()) - base - 1 };
+
+    p.extra1().exit.set(None);
+
+    let r = unsafe { zl_pcall(p.state(), n, LUA_MULTRET, 0) };
+
+    unsafe { clear_interrupt(p.state(), p.extra1()) };
+
+    if !r {
+        unsafe { map_exit(p.state(), p.extra1()) };
+        unsafe { map_error(p.state(), p.extra1()) };
+
+        return Err(unsafe { pop_error(p) });
+    }
+
+    // The exit code may be set if the snippet catch the exit request.
+    p.extra1().exit.set(None);
+
+    // Convert results. We need to pop the results ourself since Ret will release it to the parent
+    // when dropped.
+    let n = unsafe { zl_gettop(p.state()) - base };
+    let mut r = unsafe { Ret::new(p, n) };
+    let v = T::from_lua(&mut r, 1);
+
+    std::mem::forget(r);
+
+    unsafe { zl_pop(p.state(), n) };
+
+    v.ok_or(SnippetError::Result)
+}
+
+/// Push a variable captured by [`lua!`](crate::lua!).
+#[doc(hidden)]
+pub fn push_capture<P: Frame, T: IntoLua>(p: &mut P, v: T) {
+    const {
+        assert!(
+            T::N.get() == 1,
+            "captured variable must be a single Lua value"
+        )
+    };
+
+    v.into_lua(p);
+}
+
+/// # Safety
+/// Top of the stack must be an error object.
+unsafe fn pop_error<P: Frame>(p: &mut P) -> SnippetError {
+    let mut len = 0;
+    let ptr = unsafe { zl_tolstringmeta(p.state(), -1, &mut len) };
+    let msg = unsafe { std::slice::from_raw_parts(ptr.cast(), len) };
+    let msg = String::from_utf8_lossy(msg).into_owned();
+
+    unsafe { zl_pop(p.state(), 2) };
+
+    SnippetError::Run(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lua;
+
+    #[test]
+    fn run() {
+        static SITE: u8 = 0;
+
+        let mut lua = Lua::new(None).unwrap();
+        let code = b"local a, b = ...; return a, b, 3";
+
+        for _ in 0..2 {
+            let v: (String, Option<bool>, i64) =
+                run_snippet(&mut lua, &SITE, c"=test", code, |f| {
+                    push_capture(f, "abc");
+                    push_capture(f, None::<bool>);
+                })
+                .unwrap();
+
+            assert_eq!(v, ("abc".into(), None, 3));
+        }
+
+        // The chunk should be cached so the code here is ignored.
+        let v: Result<i64, _> = run_snippet(&mut lua, &SITE, c"=test", b"", |f| {
+            push_capture(f, "x");
+        });
+
+        assert_eq!(v, Err(SnippetError::Result));
+
+        let v: Result<(), _> = run_snippet(&mut lua, &SITE, c"=test", code, |_| {});
+
+        assert!(v.is_ok());
+    }
+}
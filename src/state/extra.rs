@@ -1,6 +1,32 @@
-use crate::PanicHandler;
+use crate::{
+    ChunkStore, Clock, HookFn, HookMask, PanicHandler, SourceTransformer, TransformMatch, Vfs,
+    WarnHandler,
+};
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::HashMap;
+use std::ffi::{CString, c_int};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 
 /// Data associated with all `lua_State`.
 pub struct ExtraData {
     pub panic: Box<PanicHandler>,
+    pub interrupt: OnceCell<Arc<AtomicBool>>,
+    pub hook: RefCell<Option<Box<HookFn>>>,
+    pub hook_mask: Cell<HookMask>,
+    pub warn: RefCell<Option<Box<WarnHandler>>>,
+    pub warn_on: Cell<bool>,
+    pub warn_buf: RefCell<Vec<u8>>,
+    pub vfs: RefCell<Option<Rc<dyn Vfs>>>,
+    pub exit: Cell<Option<c_int>>,
+    pub random_seed: Option<i64>,
+    pub clock: Option<Box<dyn Clock>>,
+    pub binary: Cell<bool>,
+    pub cache: RefCell<Option<Rc<dyn ChunkStore>>>,
+    pub transformers: RefCell<Vec<(TransformMatch, Rc<dyn SourceTransformer>)>>,
+    pub line_maps: RefCell<HashMap<CString, LineMap>>,
 }
+
+/// `short_src` and line mapping of a transformed chunk.
+pub type LineMap = (String, Rc<[u32]>);
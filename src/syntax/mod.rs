@@ -0,0 +1,269 @@
+use crate::ffi::LUA_IDSIZE;
+use std::ffi::CStr;
+use std::fmt::{Display, Formatter};
+
+/// Error from [`Frame::compile()`](crate::Frame::compile()).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadError {
+    /// The chunk is not valid (`LUA_ERRSYNTAX`).
+    Syntax(SyntaxError),
+    /// Not enough memory to compile the chunk (`LUA_ERRMEM`).
+    Memory,
+    /// [`ChunkType::Binary`](crate::ChunkType::Binary) was specified without
+    /// [`Lua::allow_binary()`](crate::Lua::allow_binary()).
+    Binary,
+}
+
+impl std::error::Error for LoadError {}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Syntax(e) => e.fmt(f),
+            Self::Memory => f.write_str("not enough memory"),
+            Self::Binary => f.write_str(crate::BINARY_DENIED),
+        }
+    }
+}
+
+/// Syntax error in a chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// Chunk name in the same format as the error message (e.g. `[string "..."]`).
+    pub chunk: String,
+    /// Line where the error was found. This is [`None`] for an error that does not have a
+    /// location (e.g. an invalid binary chunk).
+    pub line: Option<u32>,
+    /// The message without chunk name, line and token.
+    pub message: String,
+    /// The token where the error was found without quotes (e.g. `x` or `<eof>`).
+    pub near_token: Option<String>,
+}
+
+impl SyntaxError {
+    /// Parse `msg` produced by Lua for a chunk with `chunk` as its `short_src`.
+    pub(crate) fn parse(chunk: String, msg: &str) -> Self {
+        // Strip chunk name and line. A message without chunk name comes from Lua itself (e.g.
+        // wrong chunk type).
+        let (line, msg) = match msg.strip_prefix(chunk.as_str()) {
+            Some(v) => match v.strip_prefix(':') {
+                Some(v) => match v.split_once(": ") {
+                    Some((l, m)) => match l.parse() {
+                        Ok(l) => (Some(l), m),
+                        Err(_) => (None, v.trim_start()),
+                    },
+                    None => (None, v.trim_start()),
+                },
+                None => (None, msg),
+            },
+            None => (None, msg),
+        };
+
+        // Strip token.
+        let (message, near_token) = match msg.split_once(" near ") {
+            Some((m, t)) => {
+                let t = match t.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+                    Some(v) => v,
+                    None => t,
+                };
+
+                (m, Some(t.to_owned()))
+            }
+            None => (msg, None),
+        };
+
+        Self {
+            chunk,
+            line,
+            message: message.to_owned(),
+            near_token,
+        }
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+/// Returns `short_src` for the chunk `name`, which is the same as `luaO_chunkid`.
+///
+/// [`None`] is the same as `?`, which is what Lua use when the name is not specified.
+pub(crate) fn chunk_id(name: Option<&CStr>) -> String {
+    let src = name.map(|v| v.to_bytes()).unwrap_or(b"?");
+    let mut out = Vec::with_capacity(LUA_IDSIZE);
+
+    match src.first() {
+        Some(b'=') => {
+            // Literal source.
+            out.extend_from_slice(&src[1..src.len().min(LUA_IDSIZE)]);
+        }
+        Some(b'@') => {
+            // File name.
+            if src.len() <= LUA_IDSIZE {
+                out.extend_from_slice(&src[1..]);
+            } else {
+                let n = LUA_IDSIZE - RETS.len() - 1;
+
+                out.extend_from_slice(RETS);
+                out.extend_from_slice(&src[(src.len() - n)..]);
+            }
+        }
+        _ => {
+            // String.
+            let nl = src.iter().position(|&b| b == b'\n');
+
+            out.extend_from_slice(PRE);
+
+            if src.len() < MAX_STRING && nl.is_none() {
+                out.extend_from_slice(src);
+            } else {
+                let n = nl.unwrap_or(src.len()).min(MAX_STRING);
+
+                out.extend_from_slice(&src[..n]);
+                out.extend_from_slice(RETS);
+            }
+
+            out.extend_from_slice(POS);
+        }
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Returns `true` if [`chunk_id()`] does not contains the whole `name`.
+pub(crate) fn is_id_truncated(name: &CStr) -> bool {
+    let src = name.to_bytes();
+
+    match src.first() {
+        Some(b'=' | b'@') => src.len() > LUA_IDSIZE,
+        _ => src.len() >= MAX_STRING || src.contains(&b'\n'),
+    }
+}
+
+const RETS: &[u8] = b"...";
+const PRE: &[u8] = b"[string \"";
+const POS: &[u8] = b"\"]";
+
+/// Maximum length of a string source to be kept as-is in [`chunk_id()`].
+const MAX_STRING: usize = LUA_IDSIZE - (PRE.len() + RETS.len() + POS.len()) - 1;
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(v) => write!(f, "{}:{}: {}", self.chunk, v, self.message)?,
+            None => write!(f, "{}: {}", self.chunk, self.message)?,
+        }
+
+        match &self.near_token {
+            Some(v) if v.starts_with('<') => write!(f, " near {v}"),
+            Some(v) => write!(f, " near '{v}'"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChunkType, Frame, Lua};
+    use std::ffi::CString;
+
+    #[test]
+    fn compile() {
+        let mut lua = Lua::new(None).unwrap();
+        let e = match lua.compile(Some(c"@a:b.lua"), ChunkType::Text, "local a\nlocal 1 = x") {
+            Ok(_) => panic!("the chunk should not be valid"),
+            Err(LoadError::Syntax(e)) => e,
+            Err(e) => panic!("unexpected error {e}"),
+        };
+
+        assert_eq!(e.chunk, "a:b.lua");
+        assert_eq!(e.line, Some(2));
+        assert_eq!(e.message, "<name> expected");
+        assert_eq!(e.near_token.as_deref(), Some("1"));
+        assert_eq!(e.to_string(), "a:b.lua:2: <name> expected near '1'");
+
+        // Check error at the end of chunk.
+        let e = match lua.compile(None, ChunkType::Text, "if x then") {
+            Ok(_) => panic!("the chunk should not be valid"),
+            Err(LoadError::Syntax(e)) => e,
+            Err(e) => panic!("unexpected error {e}"),
+        };
+
+        assert_eq!(e.chunk, "[string \"?\"]");
+        assert_eq!(e.near_token.as_deref(), Some("<eof>"));
+        assert_eq!(e.to_string(), "[string \"?\"]:1: 'end' expected near <eof>");
+
+        // Check binary chunk without opt-in.
+        assert!(matches!(
+            lua.compile(None, ChunkType::Binary, "return 1"),
+            Err(LoadError::Binary)
+        ));
+
+        // Check error without location.
+        unsafe { lua.allow_binary() };
+
+        let e = match lua.compile(None, ChunkType::Binary, "return 1") {
+            Ok(_) => panic!("the chunk should not be valid"),
+            Err(LoadError::Syntax(e)) => e,
+            Err(e) => panic!("unexpected error {e}"),
+        };
+
+        assert_eq!(e.line, None);
+        assert!(e.message.starts_with("attempt to load a text chunk"));
+
+        assert!(lua.compile(None, ChunkType::Text, "return 1").is_ok());
+    }
+
+    #[test]
+    fn memory() {
+        let mut lua = Lua::builder().memory_limit(64 * 1024).build().unwrap();
+        let chunk = format!("return {{{}}}", "'abc' .. x, ".repeat(100000));
+
+        assert!(matches!(
+            lua.compile(None, ChunkType::Text, chunk),
+            Err(LoadError::Memory)
+        ));
+
+        // The state should still be usable.
+        assert!(lua.compile(None, ChunkType::Text, "return 1").is_ok());
+    }
+
+    #[test]
+    fn id() {
+        let long = "a".repeat(100);
+        let names = [
+            None,
+            Some(c"=main".to_owned()),
+            Some(c"@main.lua".to_owned()),
+            Some(c"return 1".to_owned()),
+            Some(c"local x = 1\nreturn x".to_owned()),
+            Some(CString::new(format!("={long}")).unwrap()),
+            Some(CString::new(format!("@{long}.lua")).unwrap()),
+            Some(CString::new(long.clone()).unwrap()),
+            Some(CString::new(format!("={}", &long[..59])).unwrap()),
+            Some(CString::new(format!("={}", &long[..60])).unwrap()),
+            Some(CString::new(format!("@{}", &long[..59])).unwrap()),
+            Some(CString::new(format!("@{}", &long[..60])).unwrap()),
+            Some(CString::new(&long[..44]).unwrap()),
+            Some(CString::new(&long[..45]).unwrap()),
+        ];
+
+        assert_eq!(chunk_id(None), "[string \"?\"]");
+        assert_eq!(chunk_id(Some(c"=main")), "main");
+        assert_eq!(chunk_id(Some(c"@main.lua")), "main.lua");
+        assert_eq!(chunk_id(Some(c"x\ny")), "[string \"x...\"]");
+
+        // Compare with Lua.
+        let mut lua = Lua::new(None).unwrap();
+
+        for name in names {
+            let id = chunk_id(name.as_deref());
+            let mut e = match lua.load(name.as_deref(), ChunkType::Text, "x x") {
+                Ok(_) => panic!("the chunk should not be valid"),
+                Err(e) => e,
+            };
+
+            assert!(id.len() < LUA_IDSIZE);
+            assert!(e.to_str().unwrap().starts_with(&format!("{id}:1:")), "{id}");
+        }
+    }
+}
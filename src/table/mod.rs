@@ -2,12 +2,13 @@ pub use self::borrowed::*;
 pub use self::frame::*;
 pub use self::key::*;
 
-use crate::ffi::{lua_State, zl_pop};
+use crate::ffi::{lua_State, zl_load, zl_pop, zl_rotate, zl_setupvalue};
 use crate::state::RawState;
-use crate::{Frame, Unknown};
-use std::ffi::c_int;
+use crate::{ChunkType, Frame, Function, Str, Unknown};
+use std::ffi::{CStr, c_int};
 use std::mem::ManuallyDrop;
 use std::ops::DerefMut;
+use std::ptr::null;
 
 mod borrowed;
 mod frame;
@@ -33,6 +34,53 @@ impl<'p, P: Frame> Table<'p, P> {
         unsafe { TableFrame::new(self, -2, key) }
     }
 
+    /// Load a Lua chunk with this table as its environment (`_ENV`) instead of the global table.
+    ///
+    /// This method consume the table so the loaded function will take its place in the parent
+    /// frame. See [`Frame::load()`] for more details and [`Frame::push_sandbox()`] to create an
+    /// environment from a list of allowed globals.
+    pub fn load_with_env(
+        self,
+        name: Option<&CStr>,
+        ty: ChunkType,
+        chunk: impl AsRef<[u8]>,
+    ) -> Result<Function<'p, P>, Str<'p, P>> {
+        let p: &'p mut P = unsafe { &mut *(ManuallyDrop::new(self).deref_mut().0 as *mut P) };
+        let name = name.map(|v| v.as_ptr()).unwrap_or(null());
+        let chunk = chunk.as_ref();
+        let mode = match ty.to_c_str(p.extra1().binary.get()) {
+            Some(v) => v,
+            None => {
+                unsafe { zl_pop(p.state(), 1) };
+                return Err(crate::binary_denied(p));
+            }
+        };
+        let ok = unsafe {
+            zl_load(
+                p.state(),
+                name,
+                chunk.as_ptr().cast(),
+                chunk.len(),
+                mode.as_ptr(),
+            )
+        };
+
+        // Move the result below the table.
+        unsafe { zl_rotate(p.state(), -2, 1) };
+
+        if !ok {
+            unsafe { zl_pop(p.state(), 1) };
+            return Err(unsafe { Str::new(p) });
+        }
+
+        // Set the first upvalue, which is _ENV. A binary chunk may not have any upvalues.
+        if unsafe { zl_setupvalue(p.state(), -2, 1).is_null() } {
+            unsafe { zl_pop(p.state(), 1) };
+        }
+
+        Ok(unsafe { Function::new(p) })
+    }
+
     #[inline(always)]
     pub fn into_unknown(self) -> Unknown<'p, P> {
         unsafe { Unknown::new(ManuallyDrop::new(self).deref_mut().0) }
@@ -64,3 +112,43 @@ impl<'p, P: Frame> From<Table<'p, P>> for Unknown<'p, P> {
         value.into_unknown()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{ChunkType, Frame, Lua};
+
+    #[test]
+    fn sandbox() {
+        let mut lua = Lua::new(None).unwrap();
+
+        lua.require_base();
+        lua.require_math(true);
+        lua.require_string(true);
+
+        // Run inside the sandbox.
+        let chunk = "x = 1\n\
+            math.pi = 1\n\
+            error(string.format('%s,%s', print == nil, string.upper == nil), 0)";
+        let env = lua.push_sandbox(&[c"error", c"math", c"string.format", c"missing"]);
+        let f = env
+            .load_with_env(Some(c"=test"), ChunkType::Text, chunk)
+            .unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("unexpected success"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "true,true");
+        drop(e);
+
+        // Check if globals untouched.
+        let chunk = "error(tostring(x) .. ',' .. tostring(math.pi == 1), 0)";
+        let f = lua.load(Some(c"=test"), ChunkType::Text, chunk).unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("unexpected success"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "nil,false");
+    }
+}
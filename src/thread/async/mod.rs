@@ -1,6 +1,9 @@
 use super::MainState;
-use crate::ffi::{ZL_REGISTRYINDEX, lua_State, zl_newthread, zl_pop, zl_ref, zl_unref};
+use crate::ffi::{
+    ZL_REGISTRYINDEX, lua_State, zl_inherithook, zl_newthread, zl_pop, zl_ref, zl_unref,
+};
 use crate::state::RawState;
+use crate::{Error, GcKind, GcMode, HookContext, HookMask, InterruptHandle};
 use std::ffi::c_int;
 use std::marker::PhantomPinned;
 use std::pin::Pin;
@@ -20,6 +23,105 @@ impl AsyncLua {
         })
     }
 
+    /// Returns a handle to interrupt a running Lua code from the other thread.
+    ///
+    /// See [`Lua::interrupt_handle()`](crate::Lua::interrupt_handle()) for more details.
+    #[inline(always)]
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.state.interrupt_handle()
+    }
+
+    /// Set a Rust function to be called when one of the events in `mask` is triggered.
+    ///
+    /// See [`Lua::set_hook()`](crate::Lua::set_hook()) for more details. The hook will apply to
+    /// the existing [`AsyncThread`] on the next call to
+    /// [`Function::into_async()`](crate::Function::into_async()).
+    ///
+    /// # Panics
+    /// If called from the hook itself.
+    #[inline(always)]
+    pub fn set_hook<F>(&self, mask: HookMask, f: F)
+    where
+        F: FnMut(&mut HookContext) -> Result<(), Error> + 'static,
+    {
+        self.state.set_hook(mask, f);
+    }
+
+    /// Remove the hook that was set with [`Self::set_hook()`].
+    ///
+    /// # Panics
+    /// If called from the hook itself.
+    #[inline(always)]
+    pub fn remove_hook(&self) {
+        self.state.remove_hook();
+    }
+
+    /// Set a Rust function to receive Lua warnings.
+    ///
+    /// See [`Lua::set_warn_handler()`](crate::Lua::set_warn_handler()) for more details.
+    ///
+    /// # Panics
+    /// If called from the warning handler itself.
+    #[inline(always)]
+    pub fn set_warn_handler(&self, f: impl Fn(&str) + 'static) {
+        self.state.set_warn_handler(Box::new(f));
+    }
+
+    /// Returns the exit code from the last `os.exit` called by Lua and clear it.
+    ///
+    /// See [`Lua::take_exit_code()`](crate::Lua::take_exit_code()) for more details.
+    #[inline(always)]
+    pub fn take_exit_code(&self) -> Option<c_int> {
+        self.state.take_exit_code()
+    }
+
+    /// Performs a full garbage-collection cycle.
+    ///
+    /// See [`Lua::gc_collect()`](crate::Lua::gc_collect()) for more details.
+    #[inline(always)]
+    pub fn gc_collect(&self) {
+        self.state.gc_collect();
+    }
+
+    /// Performs an incremental step of garbage collection corresponding to the allocation of
+    /// `kb` KBytes. Specify zero for a single basic step. In generational mode this performs a
+    /// full minor collection when `kb` is zero.
+    ///
+    /// Returns `true` if the step finished a collection cycle.
+    #[inline(always)]
+    pub fn gc_step(&self, kb: c_int) -> bool {
+        self.state.gc_step(kb)
+    }
+
+    /// Stops the garbage collector until [`Self::gc_restart()`].
+    #[inline(always)]
+    pub fn gc_stop(&self) {
+        self.state.gc_stop();
+    }
+
+    #[inline(always)]
+    pub fn gc_restart(&self) {
+        self.state.gc_restart();
+    }
+
+    /// Returns `true` if the garbage collector is not stopped.
+    #[inline(always)]
+    pub fn gc_running(&self) -> bool {
+        self.state.gc_running()
+    }
+
+    /// Returns the total memory in use by Lua in bytes.
+    #[inline(always)]
+    pub fn memory_used(&self) -> usize {
+        self.state.memory_used()
+    }
+
+    /// Switch garbage collector to `mode` and returns the previous mode.
+    #[inline(always)]
+    pub fn set_gc_mode(&self, mode: GcMode) -> GcKind {
+        self.state.set_gc_mode(mode)
+    }
+
     pub fn spawn(self: &Pin<Rc<Self>>) -> AsyncThread {
         let state = unsafe { zl_newthread(self.state.get()) };
         let index = unsafe { zl_ref(self.state.get(), ZL_REGISTRYINDEX) };
@@ -39,6 +141,14 @@ pub struct AsyncThread {
     index: c_int,
 }
 
+impl AsyncThread {
+    /// Copy the hook from the main thread in case it was changed after this thread was spawned.
+    #[inline(always)]
+    pub(crate) fn sync_hook(&mut self) {
+        unsafe { zl_inherithook(self.state, self.main.state.get()) };
+    }
+}
+
 impl Drop for AsyncThread {
     fn drop(&mut self) {
         unsafe { zl_unref(self.main.state.get(), ZL_REGISTRYINDEX, self.index) };
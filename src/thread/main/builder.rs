@@ -0,0 +1,243 @@
+use super::Lua;
+use crate::clock::Deterministic;
+use crate::state::RawState;
+use crate::{
+    AsyncLua, ChunkStore, Clock, Frame, GcMode, PanicHandler, SourceTransformer, TransformMatch,
+    UserType, Vfs, WarnHandler,
+};
+use std::pin::Pin;
+use std::rc::Rc;
+
+/// Builder to create [`Lua`] or [`AsyncLua`].
+///
+/// Use [`Lua::builder()`] to create this struct.
+#[derive(Default)]
+pub struct LuaBuilder {
+    panic: Option<Box<PanicHandler>>,
+    warn: Option<Box<WarnHandler>>,
+    limit: Option<usize>,
+    gc: Option<GcMode>,
+    binary: bool,
+    cache: Option<Box<Setup>>,
+    det: Option<Deterministic>,
+    libs: Vec<Library>,
+    vfs: Option<Box<Setup>>,
+    setups: Vec<Box<Setup>>,
+}
+
+impl LuaBuilder {
+    /// Set a handler for Lua panic. See [`Lua::new()`] for more details.
+    pub fn panic(mut self, f: impl Fn(Option<&str>) + 'static) -> Self {
+        self.panic = Some(Box::new(f));
+        self
+    }
+
+    /// Set a handler for Lua warnings.
+    ///
+    /// Without this Lua warnings will be written to stderr after Lua code turn it on with
+    /// `warn("@on")`. With this the warnings is on by default.
+    pub fn warn(mut self, f: impl Fn(&str) + 'static) -> Self {
+        self.warn = Some(Box::new(f));
+        self
+    }
+
+    /// Set maximum number of bytes Lua can allocate. Lua will raise a memory error when the limit
+    /// has been reached.
+    pub fn memory_limit(mut self, bytes: usize) -> Self {
+        self.limit = Some(bytes);
+        self
+    }
+
+    pub fn gc_mode(mut self, mode: GcMode) -> Self {
+        self.gc = Some(mode);
+        self
+    }
+
+    /// Enable deterministic mode, which make the same script behave the same across runs and
+    /// machines.
+    ///
+    /// This fix the seed of string hashing and seed `math.random` with `seed`. `os.time`,
+    /// `os.clock` and `os.date` will use `clock` instead of the system clock and `os.date` will
+    /// always use UTC. See [`NONDETERMINISTIC`](crate::NONDETERMINISTIC) for the functions that
+    /// remain nondeterministic.
+    pub fn deterministic(mut self, seed: i64, clock: impl Clock) -> Self {
+        self.det = Some(Deterministic {
+            seed,
+            clock: Box::new(clock),
+        });
+        self
+    }
+
+    /// Allow loading a binary chunk. See [`Lua::allow_binary()`] for more details.
+    ///
+    /// # Safety
+    /// All binary chunks must come from a trusted source.
+    pub unsafe fn allow_binary(mut self) -> Self {
+        self.binary = true;
+        self
+    }
+
+    /// Set a store to cache compiled chunks. See [`Lua::set_chunk_cache()`] for more details.
+    ///
+    /// # Safety
+    /// `store` must not be writable by an untrusted party.
+    pub unsafe fn chunk_cache(mut self, store: impl ChunkStore) -> Self {
+        self.cache = Some(Box::new(move |lua| unsafe { lua.set_chunk_cache(store) }));
+        self
+    }
+
+    /// Load the specified standard libraries. All libraries will be put to global environment.
+    ///
+    /// This replace the libraries from the previous call.
+    pub fn libs(mut self, libs: impl IntoIterator<Item = Library>) -> Self {
+        self.libs = libs.into_iter().collect();
+        self
+    }
+
+    /// Shortcut of [`Self::libs()`] with [`Library::ALL`].
+    pub fn all_libs(self) -> Self {
+        self.libs(Library::ALL)
+    }
+
+    /// Shortcut of [`Self::libs()`] with [`Library::SAFE`].
+    pub fn safe_libs(self) -> Self {
+        self.libs(Library::SAFE)
+    }
+
+    /// Set a [`Vfs`] for the libraries. See [`Lua::set_vfs()`] for more details.
+    ///
+    /// The [`Vfs`] will be set right after the libraries have been loaded.
+    pub fn vfs(mut self, vfs: impl Vfs) -> Self {
+        self.vfs = Some(Box::new(move |lua| lua.set_vfs(vfs)));
+        self
+    }
+
+    /// Register a type of full userdata. See [`Frame::register_ud()`] for more details.
+    pub fn register_ud<T: UserType>(self) -> Self {
+        self.setup(|lua| lua.register_ud::<T>())
+    }
+
+    /// Register a [`SourceTransformer`]. See [`Lua::add_transformer()`] for more details.
+    pub fn transformer(self, m: TransformMatch, t: impl SourceTransformer) -> Self {
+        self.setup(move |lua| lua.add_transformer(m, t))
+    }
+
+    /// Call `f` after all libraries have been loaded, which can be used to register modules.
+    ///
+    /// All functions will be called in the same order as they were specified, including
+    /// [`Self::register_ud()`].
+    pub fn setup(mut self, f: impl FnOnce(&mut Lua) + 'static) -> Self {
+        self.setups.push(Box::new(f));
+        self
+    }
+
+    /// Returns [`None`] if `lua_newstate` return null.
+    pub fn build(self) -> Option<Lua> {
+        let mut lua = Lua::with_options(self.panic, self.warn, self.limit, self.det)?;
+
+        if self.binary {
+            unsafe { lua.allow_binary() };
+        }
+
+        if let Some(f) = self.cache {
+            f(&mut lua);
+        }
+
+        if let Some(v) = self.gc {
+            unsafe { v.apply(lua.state()) };
+        }
+
+        for lib in self.libs {
+            lib.load(&mut lua);
+        }
+
+        if let Some(f) = self.vfs {
+            f(&mut lua);
+        }
+
+        for f in self.setups {
+            f(&mut lua);
+        }
+
+        Some(lua)
+    }
+
+    /// Returns [`None`] if `lua_newstate` return null.
+    #[inline(always)]
+    pub fn build_async(self) -> Option<Pin<Rc<AsyncLua>>> {
+        self.build().map(Lua::into_async)
+    }
+}
+
+type Setup = dyn FnOnce(&mut Lua);
+
+/// Lua standard library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Library {
+    Base,
+    /// Basic library without `dofile` and `loadfile`, which are restored by [`LuaBuilder::vfs()`]
+    /// or [`Lua::set_vfs()`] to access only the [`Vfs`].
+    BaseNoFile,
+    Coroutine,
+    Debug,
+    Io,
+    Math,
+    Os,
+    /// Package library with C loaders enabled.
+    Package,
+    /// Package library with C loaders disabled. See [`Frame::require_package()`] for more details.
+    PackageNoNative,
+    String,
+    Table,
+    Utf8,
+}
+
+impl Library {
+    pub const ALL: [Self; 10] = [
+        Self::Base,
+        Self::Package,
+        Self::Coroutine,
+        Self::Table,
+        Self::Io,
+        Self::Os,
+        Self::String,
+        Self::Math,
+        Self::Utf8,
+        Self::Debug,
+    ];
+
+    /// Libraries that does not have access to the outside of Lua.
+    ///
+    /// This use [`Library::BaseNoFile`] so `dofile` and `loadfile` are only available with a
+    /// [`Vfs`].
+    pub const SAFE: [Self; 6] = [
+        Self::BaseNoFile,
+        Self::Coroutine,
+        Self::Table,
+        Self::String,
+        Self::Math,
+        Self::Utf8,
+    ];
+
+    fn load(self, lua: &mut Lua) {
+        match self {
+            Self::Base => drop(lua.require_base()),
+            Self::BaseNoFile => {
+                let mut t = lua.require_base();
+
+                t.set(c"dofile").push_nil();
+                t.set(c"loadfile").push_nil();
+            }
+            Self::Coroutine => drop(lua.require_coroutine(true)),
+            Self::Debug => drop(lua.require_debug(true)),
+            Self::Io => drop(lua.require_io(true)),
+            Self::Math => drop(lua.require_math(true)),
+            Self::Os => drop(lua.require_os(true)),
+            Self::Package => drop(lua.require_package(true, true)),
+            Self::PackageNoNative => drop(lua.require_package(true, false)),
+            Self::String => drop(lua.require_string(true)),
+            Self::Table => drop(lua.require_table(true)),
+            Self::Utf8 => drop(lua.require_utf8(true)),
+        }
+    }
+}
@@ -1,33 +1,53 @@
+pub use self::builder::*;
 pub(crate) use self::state::*;
 
 use super::AsyncLua;
+use crate::clock::Deterministic;
 use crate::ffi::{lua_State, zl_atpanic, zl_getextraspace, zl_pop, zl_tolstring, zl_type};
 use crate::state::{ExtraData, RawState};
-use crate::{PanicHandler, Type};
+use crate::{
+    ChunkStore, Error, GcKind, GcMode, HookContext, HookMask, InterruptHandle, PanicHandler,
+    SourceTransformer, TransformMatch, Type, Vfs, WarnHandler,
+};
 use std::backtrace::Backtrace;
 use std::ffi::c_int;
 use std::pin::Pin;
 use std::rc::Rc;
 
+mod builder;
 mod state;
 
 /// Encapsulates a `lua_State` created by `lua_newstate`.
 pub struct Lua(MainState);
 
 impl Lua {
-    /// Create a new `lua_State` using `luaL_newstate`. Returns [`None`] if `luaL_newstate` return
+    /// Create a new `lua_State` using `lua_newstate`. Returns [`None`] if `lua_newstate` return
     /// null.
     ///
     /// Specify [`Some`] for `panic` if you want a custom handler for Lua panic otherwise Zero Lua
     /// will provides a default one that print the panic to stderr.
     ///
-    /// You may want to change Lua warning function after this if your application is a GUI
-    /// application.
+    /// Use [`Self::builder()`] if you want to change Lua warning function or other settings.
     ///
     /// Use [`Self::into_async()`] to convert this type to [`AsyncLua`] if you need to call into
     /// Rust async function or Lua function that yield.
     #[inline(always)]
     pub fn new(panic: Option<Box<PanicHandler>>) -> Option<Self> {
+        Self::with_options(panic, None, None, None)
+    }
+
+    /// Returns a [`LuaBuilder`] to configure a new `lua_State`.
+    #[inline(always)]
+    pub fn builder() -> LuaBuilder {
+        LuaBuilder::default()
+    }
+
+    fn with_options(
+        panic: Option<Box<PanicHandler>>,
+        warn: Option<Box<WarnHandler>>,
+        limit: Option<usize>,
+        det: Option<Deterministic>,
+    ) -> Option<Self> {
         // Get panic handler.
         let panic = panic.unwrap_or_else(|| {
             Box::new(|msg| {
@@ -41,13 +61,184 @@ impl Lua {
         });
 
         // Initialize lua_State.
-        let state = MainState::new(panic)?;
+        let state = MainState::new(panic, warn, limit, det)?;
 
         unsafe { zl_atpanic(state.get(), Some(Self::panic)) };
 
         Some(Self(state))
     }
 
+    /// Returns a handle to interrupt a running Lua code from the other thread.
+    ///
+    /// The first call of this method will install a count hook to this `lua_State`, which will
+    /// slightly reduce the performance.
+    #[inline(always)]
+    pub fn interrupt_handle(&mut self) -> InterruptHandle {
+        self.0.interrupt_handle()
+    }
+
+    /// Set a Rust function to be called when one of the events in `mask` is triggered.
+    ///
+    /// This replace the previous hook if any. The hook can raise a Lua error by returning
+    /// [`Err`]. When running inside [`AsyncThread`](crate::AsyncThread) the hook can also request
+    /// the thread to yield with [`HookContext::request_yield()`].
+    ///
+    /// Coroutines inherit the hook from the thread that create it at the time it was created so
+    /// the coroutines that already exist will not be affected by this call. Note that Lua does not
+    /// trigger a hook while running a hook.
+    #[inline(always)]
+    pub fn set_hook<F>(&mut self, mask: HookMask, f: F)
+    where
+        F: FnMut(&mut HookContext) -> Result<(), Error> + 'static,
+    {
+        self.0.set_hook(mask, f);
+    }
+
+    /// Remove the hook that was set with [`Self::set_hook()`].
+    #[inline(always)]
+    pub fn remove_hook(&mut self) {
+        self.0.remove_hook();
+    }
+
+    /// Set a Rust function to receive Lua warnings, including the one from `warn` function.
+    ///
+    /// This replace the previous handler and turn the warnings on. Lua code can still turn it off
+    /// with `warn("@off")`.
+    #[inline(always)]
+    pub fn set_warn_handler(&mut self, f: impl Fn(&str) + 'static) {
+        self.0.set_warn_handler(Box::new(f));
+    }
+
+    /// Performs a full garbage-collection cycle.
+    ///
+    /// This use `lua_gc` with `LUA_GCCOLLECT` under the hood.
+    #[inline(always)]
+    pub fn gc_collect(&mut self) {
+        self.0.gc_collect();
+    }
+
+    /// Performs an incremental step of garbage collection corresponding to the allocation of
+    /// `kb` KBytes. Specify zero for a single basic step. In generational mode this performs a
+    /// full minor collection when `kb` is zero.
+    ///
+    /// Returns `true` if the step finished a collection cycle.
+    #[inline(always)]
+    pub fn gc_step(&mut self, kb: c_int) -> bool {
+        self.0.gc_step(kb)
+    }
+
+    /// Stops the garbage collector until [`Self::gc_restart()`].
+    #[inline(always)]
+    pub fn gc_stop(&mut self) {
+        self.0.gc_stop();
+    }
+
+    #[inline(always)]
+    pub fn gc_restart(&mut self) {
+        self.0.gc_restart();
+    }
+
+    /// Returns `true` if the garbage collector is not stopped.
+    #[inline(always)]
+    pub fn gc_running(&mut self) -> bool {
+        self.0.gc_running()
+    }
+
+    /// Returns the total memory in use by Lua in bytes.
+    #[inline(always)]
+    pub fn memory_used(&mut self) -> usize {
+        self.0.memory_used()
+    }
+
+    /// Switch garbage collector to `mode` and returns the previous mode.
+    #[inline(always)]
+    pub fn set_gc_mode(&mut self, mode: GcMode) -> GcKind {
+        self.0.set_gc_mode(mode)
+    }
+
+    /// Allow [`Frame::load()`](crate::Frame::load()) and the other methods to load a binary
+    /// chunk (e.g. the output of [`Function::dump()`](crate::Function::dump())).
+    ///
+    /// This also allow `load`, `loadfile` and `dofile` from
+    /// [`Frame::require_base()`](crate::Frame::require_base()) to load a binary chunk, which
+    /// always use text mode otherwise.
+    ///
+    /// # Safety
+    /// Lua does not verify a binary chunk so malformed or malicious bytecode can cause undefined
+    /// behavior. All binary chunks must come from a trusted source.
+    #[inline(always)]
+    pub unsafe fn allow_binary(&mut self) {
+        self.0.allow_binary();
+    }
+
+    /// Cache compiled chunks from [`Frame::load_file()`](crate::Frame::load_file()) into `store`.
+    ///
+    /// `loadfile` and `dofile` only use the cache with [`Self::set_vfs()`] and `require` only use
+    /// it when the module was found through the VFS or a [`Searcher`](crate::Searcher). The stock
+    /// functions read the real filesystem by themselves so they bypass the cache.
+    ///
+    /// Each entry is validated with the chunk name, modification time and hash of the source and
+    /// Lua version. The source will be compiled again and the entry will be replaced if any of
+    /// these does not match. This replace the previous store.
+    ///
+    /// # Safety
+    /// The bytecode from `store` will be loaded without any verification the same as
+    /// [`Self::allow_binary()`] so `store` must not be writable by an untrusted party.
+    pub unsafe fn set_chunk_cache(&mut self, store: impl ChunkStore) {
+        *self.extra1().cache.borrow_mut() = Some(Rc::new(store));
+    }
+
+    /// Register `t` to transform the chunks that matched with `m` before compiling.
+    ///
+    /// The transformers will be tried in the same order as they were added. Error messages from
+    /// [`Function::call()`](crate::Function::call()) will have the line numbers mapped to the
+    /// original source. Use [`Self::map_lines()`] for the other messages like a traceback.
+    ///
+    /// `require`, `loadfile` and `dofile` only use the transformers when they are routed through
+    /// [`Self::set_vfs()`] or the module come from [`Searcher`](crate::Searcher). See
+    /// [`SourceTransformer`] for more details.
+    pub fn add_transformer(&mut self, m: TransformMatch, t: impl SourceTransformer) {
+        self.extra1()
+            .transformers
+            .borrow_mut()
+            .push((m, Rc::new(t)));
+    }
+
+    /// Replace the locations in `msg` that point to a transformed chunk with the location in the
+    /// original source.
+    pub fn map_lines(&mut self, msg: &str) -> String {
+        match crate::transform::map_lines(self.extra1(), msg) {
+            Some(v) => v,
+            None => msg.to_owned(),
+        }
+    }
+
+    /// Returns the exit code from the last `os.exit` called by Lua and clear it.
+    ///
+    /// `os.exit` from [`Frame::require_safe_os()`](crate::Frame::require_safe_os()) does not terminate the process but raise an
+    /// error with `exit requested with code N` as a message instead. Use this method after the
+    /// call failed to distinguish the exit request from the other errors. The code is only
+    /// available when the call failed because of `os.exit` and it is cleared at the start of each
+    /// call.
+    #[inline(always)]
+    pub fn take_exit_code(&mut self) -> Option<c_int> {
+        self.0.take_exit_code()
+    }
+
+    /// Route [`Frame::load_file()`](crate::Frame::load_file()), `loadfile`, `dofile`, the io
+    /// functions that take a path and the Lua searcher of `require` through `vfs`. See [`Vfs`]
+    /// for the functions that will be disabled.
+    ///
+    /// Only the libraries that already loaded will be affected so this should be called after
+    /// loading the libraries. This replace the previous [`Vfs`].
+    pub fn set_vfs(&mut self, vfs: impl Vfs) {
+        let vfs: Rc<dyn Vfs> = Rc::new(vfs);
+
+        crate::vfs::install_vfs(self, &vfs);
+
+        *self.extra1().vfs.borrow_mut() = Some(vfs);
+    }
+
     pub fn into_async(self) -> Pin<Rc<AsyncLua>> {
         AsyncLua::new(self.0)
     }
@@ -85,3 +276,138 @@ impl RawState for Lua {
         unsafe { zl_pop(self.0.get(), n) };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChunkType, Frame, Library, MemoryFs};
+    use std::cell::RefCell;
+
+    #[test]
+    fn builder() {
+        let warns = Rc::new(RefCell::new(Vec::new()));
+        let w = warns.clone();
+        let mut lua = Lua::builder()
+            .safe_libs()
+            .memory_limit(1024 * 1024)
+            .warn(move |m| w.borrow_mut().push(m.to_owned()))
+            .build()
+            .unwrap();
+
+        // Check libraries and warnings.
+        let chunk = "warn('a', 'b')\n\
+            warn('@off')\n\
+            warn('c')\n\
+            warn('@on')\n\
+            warn('d')\n\
+            error(type(io) .. ',' .. type(string), 0)";
+        let f = lua.load(Some(c"=test"), ChunkType::Text, chunk).unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("unexpected success"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "nil,table");
+        assert_eq!(*warns.borrow(), ["ab", "d"]);
+        drop(e);
+
+        // Check memory limit.
+        let chunk = "local t = {} for i = 1, 1000000 do t[i] = i end";
+        let f = lua.load(Some(c"=test"), ChunkType::Text, chunk).unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("unexpected success"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "not enough memory");
+        drop(e);
+
+        // Check the functions that access the filesystem.
+        let chunk = "return type(dofile) .. ',' .. type(loadfile) .. ',' .. type(load)";
+        let f = lua.load(Some(c"=test"), ChunkType::Text, chunk).unwrap();
+        let mut r = f.call().ok().unwrap();
+
+        assert_eq!(r.to_bytes(1), Some(b"nil,nil,function".as_slice()));
+        drop(r);
+
+        // The VFS should restore it.
+        let mut lua = Lua::builder()
+            .safe_libs()
+            .vfs(MemoryFs::new())
+            .build()
+            .unwrap();
+        let f = lua.load(Some(c"=test"), ChunkType::Text, chunk).unwrap();
+        let mut r = f.call().ok().unwrap();
+
+        assert_eq!(
+            r.to_bytes(1),
+            Some(b"function,function,function".as_slice())
+        );
+    }
+
+    #[test]
+    fn package() {
+        let chunk = "assert(type(require) == 'function')\n\
+            return type(package.loadlib), package.cpath";
+
+        for (lib, native) in [(Library::Package, true), (Library::PackageNoNative, false)] {
+            let mut lua = Lua::builder().libs([Library::Base, lib]).build().unwrap();
+            let f = lua.load(Some(c"=test"), ChunkType::Text, chunk).unwrap();
+            let mut r = f.call().ok().unwrap();
+
+            assert_eq!(r.to_bytes(1) == Some(b"function"), native);
+            assert_eq!(r.to_bytes(2) == Some(b""), !native);
+        }
+    }
+
+    #[test]
+    fn warn() {
+        let warns = Rc::new(RefCell::new(Vec::new()));
+        let w = warns.clone();
+        let mut lua = Lua::new(None).unwrap();
+
+        lua.require_base();
+        lua.set_warn_handler(move |m| w.borrow_mut().push(m.to_owned()));
+
+        let f = lua
+            .load(Some(c"=test"), ChunkType::Text, "warn('x', 'y', 'z')")
+            .unwrap();
+
+        assert!(f.call().is_ok());
+        assert_eq!(*warns.borrow(), ["xyz"]);
+    }
+
+    #[test]
+    fn gc() {
+        let mut lua = Lua::new(None).unwrap();
+        let mode = GcMode::Generational {
+            minor_mul: 0,
+            major_mul: 0,
+        };
+
+        assert!(lua.memory_used() > 0);
+        assert_eq!(lua.set_gc_mode(mode), GcKind::Incremental);
+        assert_eq!(lua.set_gc_mode(mode), GcKind::Generational);
+
+        lua.gc_stop();
+        assert!(!lua.gc_running());
+
+        // Allocate garbage.
+        let before = lua.memory_used();
+        let f = lua
+            .load(None, ChunkType::Text, "for i = 1, 100 do local t = {} end")
+            .unwrap();
+
+        assert!(f.call().is_ok());
+
+        let peak = lua.memory_used();
+
+        assert!(peak > before);
+
+        lua.gc_restart();
+        lua.gc_collect();
+
+        assert!(lua.gc_running());
+        assert!(lua.memory_used() < peak);
+    }
+}
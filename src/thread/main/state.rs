@@ -1,14 +1,34 @@
-use crate::PanicHandler;
-use crate::ffi::{lua_State, zl_close, zl_getextraspace, zl_newstate};
+use crate::clock::Deterministic;
+use crate::ffi::{
+    LUA_GCCOLLECT, LUA_GCCOUNT, LUA_GCCOUNTB, LUA_GCISRUNNING, LUA_GCRESTART, LUA_GCSTEP,
+    LUA_GCSTOP, lua_State, zl_close, zl_gc, zl_getextraspace, zl_newstate, zl_setwarnf,
+};
 use crate::state::ExtraData;
+use crate::{
+    Error, GcKind, GcMode, HookContext, HookMask, InterruptHandle, PanicHandler, WarnHandler,
+    install, warn,
+};
+use std::cell::{Cell, OnceCell, RefCell};
+use std::ffi::{c_int, c_uint};
+use std::hash::{BuildHasher, RandomState};
 
 /// Encapsulates [`State`] created from `lua_newstate`.
 pub struct MainState(*mut lua_State);
 
 impl MainState {
-    pub(super) fn new(panic: Box<PanicHandler>) -> Option<Self> {
+    pub(super) fn new(
+        panic: Box<PanicHandler>,
+        warn: Option<Box<WarnHandler>>,
+        limit: Option<usize>,
+        det: Option<Deterministic>,
+    ) -> Option<Self> {
         // Create lua_State.
-        let state = zl_newstate();
+        let seed = match &det {
+            Some(v) => ((v.seed >> 32) ^ v.seed) as c_uint,
+            None => RandomState::new().hash_one(0) as c_uint,
+        };
+
+        let state = zl_newstate(limit.unwrap_or(usize::MAX), seed);
         let state = if state.is_null() {
             return None;
         } else {
@@ -17,9 +37,27 @@ impl MainState {
 
         // Set extra data.
         let space = unsafe { zl_getextraspace(state.0).cast::<*mut ExtraData>() };
-        let extra = Box::new(ExtraData { panic });
+        let extra = Box::new(ExtraData {
+            panic,
+            interrupt: OnceCell::new(),
+            hook: RefCell::new(None),
+            hook_mask: Cell::default(),
+            warn_on: Cell::new(warn.is_some()),
+            warn: RefCell::new(warn),
+            warn_buf: RefCell::default(),
+            vfs: RefCell::default(),
+            exit: Cell::default(),
+            random_seed: det.as_ref().map(|v| v.seed),
+            clock: det.map(|v| v.clock),
+            binary: Cell::new(false),
+            cache: RefCell::default(),
+            transformers: RefCell::default(),
+            line_maps: RefCell::default(),
+        });
+        let extra = Box::into_raw(extra);
 
-        unsafe { space.write(Box::into_raw(extra)) };
+        unsafe { space.write(extra) };
+        unsafe { zl_setwarnf(state.0, Some(self::warn), extra.cast()) };
 
         Some(state)
     }
@@ -27,19 +65,114 @@ impl MainState {
     pub fn get(&self) -> *mut lua_State {
         self.0
     }
+
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        let ex = self.extra();
+        let flag = match ex.interrupt.get() {
+            Some(v) => v.clone(),
+            None => {
+                let v = ex.interrupt.get_or_init(Default::default).clone();
+                unsafe { install(self.0, ex) };
+                v
+            }
+        };
+
+        InterruptHandle::new(flag)
+    }
+
+    /// # Panics
+    /// If called from the hook itself.
+    pub fn set_hook<F>(&self, mask: HookMask, f: F)
+    where
+        F: FnMut(&mut HookContext) -> Result<(), Error> + 'static,
+    {
+        let ex = self.extra();
+
+        *ex.hook.borrow_mut() = Some(Box::new(f));
+        ex.hook_mask.set(mask);
+
+        unsafe { install(self.0, ex) };
+    }
+
+    /// # Panics
+    /// If called from the hook itself.
+    pub fn remove_hook(&self) {
+        let ex = self.extra();
+        let f = ex.hook.borrow_mut().take();
+
+        ex.hook_mask.set(HookMask::default());
+
+        unsafe { install(self.0, ex) };
+
+        drop(f);
+    }
+
+    /// # Panics
+    /// If called from the warning handler itself.
+    pub fn set_warn_handler(&self, f: Box<WarnHandler>) {
+        let ex = self.extra();
+        let prev = ex.warn.borrow_mut().replace(f);
+
+        ex.warn_on.set(true);
+
+        drop(prev);
+    }
+
+    pub fn gc_collect(&self) {
+        unsafe { zl_gc(self.0, LUA_GCCOLLECT, 0, 0, 0) };
+    }
+
+    pub fn gc_step(&self, kb: c_int) -> bool {
+        unsafe { zl_gc(self.0, LUA_GCSTEP, kb, 0, 0) != 0 }
+    }
+
+    pub fn gc_stop(&self) {
+        unsafe { zl_gc(self.0, LUA_GCSTOP, 0, 0, 0) };
+    }
+
+    pub fn gc_restart(&self) {
+        unsafe { zl_gc(self.0, LUA_GCRESTART, 0, 0, 0) };
+    }
+
+    pub fn gc_running(&self) -> bool {
+        unsafe { zl_gc(self.0, LUA_GCISRUNNING, 0, 0, 0) != 0 }
+    }
+
+    pub fn memory_used(&self) -> usize {
+        let kb = unsafe { zl_gc(self.0, LUA_GCCOUNT, 0, 0, 0) };
+        let b = unsafe { zl_gc(self.0, LUA_GCCOUNTB, 0, 0, 0) };
+
+        (kb as usize) * 1024 + (b as usize)
+    }
+
+    pub fn set_gc_mode(&self, mode: GcMode) -> GcKind {
+        unsafe { mode.apply(self.0) }
+    }
+
+    pub fn allow_binary(&self) {
+        self.extra().binary.set(true);
+    }
+
+    pub fn take_exit_code(&self) -> Option<c_int> {
+        self.extra().exit.take()
+    }
+
+    fn extra(&self) -> &ExtraData {
+        unsafe { &*zl_getextraspace(self.0).cast::<*const ExtraData>().read() }
+    }
 }
 
 impl Drop for MainState {
     fn drop(&mut self) {
-        // Free extra data.
+        // Free lua_State first since finalizers may trigger a warning or a hook.
         let extra = unsafe { zl_getextraspace(self.0).cast::<*mut ExtraData>() };
         let extra = unsafe { extra.read() };
 
+        unsafe { zl_close(self.0) };
+
+        // Free extra data.
         if !extra.is_null() {
             drop(unsafe { Box::from_raw(extra) });
         }
-
-        // Free lua_State.
-        unsafe { zl_close(self.0) };
     }
 }
@@ -0,0 +1,366 @@
+use crate::Frame;
+use crate::Type;
+use crate::ffi::{lua_State, zl_pop, zl_pushlstring, zl_rotate, zl_tolstring, zl_type};
+use crate::state::ExtraData;
+use crate::syntax::{chunk_id, is_id_truncated};
+use std::borrow::Cow;
+use std::ffi::CStr;
+use std::fmt::Write;
+use std::path::Path;
+use std::rc::Rc;
+
+/// Compiler from another language to Lua (e.g. Fennel or MoonScript).
+///
+/// Use [`Lua::add_transformer()`](crate::Lua::add_transformer()) to register the implementation.
+/// The transformer will be used by [`Frame::load_file()`], [`Searcher`](crate::Searcher) and the
+/// functions routed through [`Lua::set_vfs()`](crate::Lua::set_vfs()) (e.g. `loadfile`, `dofile`
+/// and the Lua searcher of `require`). Without a [`Vfs`](crate::Vfs) the stock `loadfile`, `dofile`
+/// and `require` read the file directly so the transformer will not be used.
+pub trait SourceTransformer: 'static {
+    /// Compile `source` of the chunk `name` to Lua code.
+    ///
+    /// `name` is a chunk name without `@` or `=` (e.g. a path of the file). The error will be
+    /// reported to Lua as a load error.
+    fn transform(&self, name: &str, source: &[u8]) -> Result<Transformed, String>;
+}
+
+/// Output of [`SourceTransformer`].
+pub struct Transformed {
+    /// Lua code.
+    pub code: Vec<u8>,
+    /// Line in the original source for each line of [`Self::code`] where the first element is for
+    /// the first line. Lines that does not have an entry will be reported as-is.
+    pub lines: Vec<u32>,
+}
+
+/// Chunks to be handled by [`SourceTransformer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransformMatch {
+    /// Chunk name with this extension without a dot (e.g. `fnl`).
+    Extension(Cow<'static, str>),
+    /// Chunk that start with these bytes.
+    Prefix(Cow<'static, [u8]>),
+}
+
+impl TransformMatch {
+    fn is_match(&self, name: &str, data: &[u8]) -> bool {
+        match self {
+            Self::Extension(v) => Path::new(name).extension().is_some_and(|e| e == v.as_ref()),
+            Self::Prefix(v) => data.starts_with(v),
+        }
+    }
+}
+
+/// Returns a transformer for the chunk `name` with `data`.
+pub(crate) fn find(ex: &ExtraData, name: &CStr, data: &[u8]) -> Option<Rc<dyn SourceTransformer>> {
+    let list = ex.transformers.borrow();
+
+    if list.is_empty() {
+        return None;
+    }
+
+    let name = display_name(name);
+
+    list.iter()
+        .find(|(m, _)| m.is_match(&name, data))
+        .map(|(_, t)| t.clone())
+}
+
+/// Returns `name` without `@` or `=`.
+pub(crate) fn display_name(name: &CStr) -> Cow<'_, str> {
+    let name = name.to_bytes();
+    let name = match name.first() {
+        Some(b'@' | b'=') => &name[1..],
+        _ => name,
+    };
+
+    String::from_utf8_lossy(name)
+}
+
+/// Associate `lines` with the chunk `name`. Empty `lines` will remove the mapping.
+///
+/// The mapping will not be added if `short_src` of the chunk was truncated since it may be the same
+/// as the other chunks.
+pub(crate) fn set_lines<P: Frame>(p: &mut P, name: &CStr, lines: Rc<[u32]>) {
+    let mut maps = p.extra1().line_maps.borrow_mut();
+
+    if lines.is_empty() || is_id_truncated(name) {
+        if !maps.is_empty() {
+            maps.remove(name);
+        }
+
+        return;
+    }
+
+    let id = chunk_id(Some(name));
+
+    maps.insert(name.to_owned(), (id, lines));
+}
+
+/// Replace all locations (e.g. `foo.fnl:3:`) in `msg` with the original line. Returns [`None`] if
+/// no line mapping.
+pub(crate) fn map_lines(ex: &ExtraData, msg: &str) -> Option<String> {
+    let maps = ex.line_maps.borrow();
+
+    if maps.is_empty() {
+        return None;
+    }
+
+    let mut msg = Cow::Borrowed(msg);
+
+    for (id, lines) in maps.values() {
+        let pat = format!("{id}:");
+
+        if !msg.contains(&pat) {
+            continue;
+        }
+
+        let mut out = String::with_capacity(msg.len());
+        let mut rest = msg.as_ref();
+
+        while let Some(i) = rest.find(&pat) {
+            // The location must start the message or follow a separator otherwise it can be a part
+            // of the other name (e.g. `data.fnl` for `a.fnl`).
+            let prev = rest[..i].chars().next_back().or(out.chars().next_back());
+            let start = prev.is_none_or(|c| c.is_whitespace() || c == '(');
+            let (head, tail) = rest.split_at(i + pat.len());
+            let n = tail.bytes().take_while(|b| b.is_ascii_digit()).count();
+            let line = match start && tail[n..].starts_with(':') {
+                true => tail[..n].parse::<usize>().ok(),
+                false => None,
+            };
+
+            out.push_str(head);
+
+            match line.and_then(|v| lines.get(v.wrapping_sub(1))) {
+                Some(v) => {
+                    write!(out, "{v}").unwrap();
+                    rest = &tail[n..];
+                }
+                None => rest = tail,
+            }
+        }
+
+        out.push_str(rest);
+        msg = Cow::Owned(out);
+    }
+
+    match msg {
+        Cow::Borrowed(_) => None,
+        Cow::Owned(v) => Some(v),
+    }
+}
+
+/// Apply [`map_lines()`] to the error object on the top of the stack.
+///
+/// # Safety
+/// `L` must be the same as the one that own `ex` and the top of the stack must be valid.
+pub(crate) unsafe fn map_error(#[allow(non_snake_case)] L: *mut lua_State, ex: &ExtraData) {
+    if ex.line_maps.borrow().is_empty() || unsafe { zl_type(L, -1) } != Type::String {
+        return;
+    }
+
+    let msg = unsafe {
+        let mut len = 0;
+        let ptr = zl_tolstring(L, -1, &mut len);
+
+        String::from_utf8_lossy(std::slice::from_raw_parts(ptr.cast(), len))
+    };
+
+    if let Some(v) = map_lines(ex, &msg) {
+        unsafe { zl_pushlstring(L, v.as_ptr().cast(), v.len()) };
+        unsafe { zl_rotate(L, -2, 1) };
+        unsafe { zl_pop(L, 1) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChunkType, Lua, MemoryFs};
+
+    /// Convert `!` to `error` and add 2 lines header.
+    struct Bang;
+
+    impl SourceTransformer for Bang {
+        fn transform(&self, _: &str, source: &[u8]) -> Result<Transformed, String> {
+            let src = std::str::from_utf8(source).map_err(|e| e.to_string())?;
+
+            if src.contains('?') {
+                return Err("unexpected '?'".into());
+            }
+
+            let mut code = String::from("local error = error\n\n");
+            let mut lines = vec![1, 1];
+
+            for (i, l) in src.lines().enumerate() {
+                code.push_str(&l.replace('!', "error"));
+                code.push('\n');
+                lines.push(i as u32 + 1);
+            }
+
+            Ok(Transformed {
+                code: code.into_bytes(),
+                lines,
+            })
+        }
+    }
+
+    #[test]
+    fn transform() {
+        let mut lua = Lua::new(None).unwrap();
+        let mut fs = MemoryFs::new();
+
+        fs.insert("main.bang", b"local a = 1\n!('boom')");
+        fs.insert("bad.bang", b"?");
+        fs.insert("prefix.lua", b"--bang\n\n!('prefix')");
+
+        lua.require_base();
+        lua.set_vfs(fs);
+        lua.add_transformer(TransformMatch::Extension("bang".into()), Bang);
+        lua.add_transformer(TransformMatch::Prefix(b"--bang".into()), Bang);
+
+        // Check line mapping.
+        let f = lua
+            .load_file("main.bang", ChunkType::Text)
+            .unwrap()
+            .unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("the chunk should raise an error"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "main.bang:2: boom");
+        drop(e);
+
+        assert_eq!(
+            lua.map_lines("main.bang:4: in main chunk"),
+            "main.bang:2: in main chunk"
+        );
+        assert_eq!(
+            lua.map_lines("\tmain.bang:4: in (main.bang:4:)"),
+            "\tmain.bang:2: in (main.bang:2:)"
+        );
+
+        // Check location of the other chunks.
+        assert_eq!(
+            lua.map_lines("domain.bang:4: lib/main.bang:4:"),
+            "domain.bang:4: lib/main.bang:4:"
+        );
+
+        // Chunk with a truncated name should not be mapped.
+        let long = format!("{}.bang", "a".repeat(64));
+        let mut fs = MemoryFs::new();
+
+        fs.insert(&long, b"!('long')");
+
+        lua.set_vfs(fs);
+
+        let f = lua.load_file(&long, ChunkType::Text).unwrap().unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("the chunk should raise an error"),
+            Err(e) => e,
+        };
+
+        assert!(e.to_str().unwrap().ends_with("aaa.bang:3: long"));
+        drop(e);
+
+        let mut fs = MemoryFs::new();
+
+        fs.insert("main.bang", b"local a = 1\n!('boom')");
+        fs.insert("bad.bang", b"?");
+        fs.insert("prefix.lua", b"--bang\n\n!('prefix')");
+
+        lua.set_vfs(fs);
+
+        // Check prefix.
+        let f = lua
+            .load_file("prefix.lua", ChunkType::Text)
+            .unwrap()
+            .unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("the chunk should raise an error"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "prefix.lua:3: prefix");
+        drop(e);
+
+        // Check transform error.
+        let mut e = match lua.load_file("bad.bang", ChunkType::Text).unwrap() {
+            Ok(_) => panic!("the chunk should not be valid"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "bad.bang: unexpected '?'");
+        drop(e);
+
+        // Check if the mapping was cleared when the chunk no longer transformed.
+        let mut fs = MemoryFs::new();
+
+        fs.insert("prefix.lua", b"\n\nerror('plain')");
+
+        lua.set_vfs(fs);
+
+        let f = lua
+            .load_file("prefix.lua", ChunkType::Text)
+            .unwrap()
+            .unwrap();
+        let mut e = match f.call() {
+            Ok(_) => panic!("the chunk should raise an error"),
+            Err(e) => e,
+        };
+
+        assert_eq!(e.to_str().unwrap(), "prefix.lua:3: plain");
+    }
+
+    #[test]
+    fn require() {
+        let dir = std::env::temp_dir().join(format!("zl-transform-{}", std::process::id()));
+        let path = format!("{}/?.lua", dir.to_str().unwrap());
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("m.lua"), "--bang\nreturn '!'").unwrap();
+
+        // The stock searcher does not use the transformer.
+        let mut lua = Lua::new(None).unwrap();
+
+        lua.require_base();
+        lua.require_package(true, false)
+            .set(c"path")
+            .push_str(&path);
+        lua.add_transformer(TransformMatch::Prefix(b"--bang".into()), Bang);
+
+        let f = lua
+            .load(None, ChunkType::Text, "return require('m')")
+            .unwrap();
+        let mut r = f.call().ok().unwrap();
+
+        assert_eq!(r.to_bytes(1), Some(b"!".as_slice()));
+        drop(r);
+
+        // The searcher from VFS does.
+        let mut fs = MemoryFs::new();
+
+        fs.insert("m.lua", b"--bang\nreturn '!'");
+
+        lua.require_package(true, false)
+            .set(c"path")
+            .push_str("?.lua");
+        lua.set_vfs(fs);
+
+        let f = lua
+            .load(
+                None,
+                ChunkType::Text,
+                "package.loaded.m = nil\nreturn require('m')",
+            )
+            .unwrap();
+        let mut r = f.call().ok().unwrap();
+
+        assert_eq!(r.to_bytes(1), Some(b"error".as_slice()));
+        drop(r);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
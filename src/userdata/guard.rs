@@ -0,0 +1,45 @@
+use std::cell::Cell;
+use std::ops::{Deref, DerefMut};
+
+/// Mutable reference to a full userdata returned from
+/// [`Context::to_ud_mut()`](crate::Context::to_ud_mut()).
+///
+/// The userdata will be borrowed until this struct is dropped.
+pub struct UdRefMut<'a, T> {
+    borrow: &'a Cell<isize>,
+    value: &'a mut T,
+}
+
+impl<'a, T> UdRefMut<'a, T> {
+    /// # Safety
+    /// `borrow` must be a borrow flag of `value` and it must be zero.
+    #[inline(always)]
+    pub(crate) unsafe fn new(borrow: &'a Cell<isize>, value: &'a mut T) -> Self {
+        borrow.set(-1);
+
+        Self { borrow, value }
+    }
+}
+
+impl<T> Drop for UdRefMut<'_, T> {
+    #[inline(always)]
+    fn drop(&mut self) {
+        self.borrow.set(0);
+    }
+}
+
+impl<T> Deref for UdRefMut<'_, T> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<T> DerefMut for UdRefMut<'_, T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.value
+    }
+}
@@ -1,14 +1,17 @@
 pub use self::borrowed::*;
 pub use self::frame::*;
+pub use self::guard::*;
 pub use self::owned::*;
 pub use self::value::*;
 
 use crate::{Frame, GlobalSetter, Table, Value};
+use std::cell::Cell;
 use std::ffi::CStr;
 use std::num::NonZero;
 
 mod borrowed;
 mod frame;
+mod guard;
 mod owned;
 mod value;
 
@@ -18,6 +21,53 @@ pub(crate) const fn is_boxed<T: UserType>() -> bool {
     align_of::<T>() > align_of::<*mut ()>()
 }
 
+/// Memory layout of a full userdata for [`UserType`]. The value will be `Box<T>` if
+/// [`is_boxed()`] returns `true`.
+///
+/// `borrow` is the same as `RefCell`, which is zero when the value is not borrowed, positive for
+/// the number of shared borrows and -1 when mutably borrowed. It will be [`TAKEN`] when the value
+/// has been moved out with [`OwnedUd::take()`].
+#[repr(C)]
+pub(crate) struct UdCell<T> {
+    pub borrow: Cell<isize>,
+    pub value: T,
+}
+
+impl<T> UdCell<T> {
+    #[inline(always)]
+    pub fn new(value: T) -> Self {
+        Self {
+            borrow: Cell::new(0),
+            value,
+        }
+    }
+}
+
+pub(crate) const TAKEN: isize = isize::MIN;
+
+/// Returns the borrow flag of full userdata `ud`.
+///
+/// # Safety
+/// `ud` must be a full userdata for [`UserType`].
+#[inline(always)]
+pub(crate) unsafe fn ud_borrow<'a>(ud: *mut u8) -> &'a Cell<isize> {
+    // SAFETY: UdCell is repr(C) so the flag always at the beginning.
+    unsafe { &*ud.cast::<Cell<isize>>() }
+}
+
+/// Returns a pointer to the value of full userdata `ud`.
+///
+/// # Safety
+/// `ud` must be a full userdata for `T` and the value must not be [`TAKEN`].
+#[inline(always)]
+pub(crate) unsafe fn ud_value<T: UserType>(ud: *mut u8) -> *mut T {
+    if is_boxed::<T>() {
+        unsafe { (*ud.cast::<UdCell<Box<T>>>()).value.as_mut() }
+    } else {
+        unsafe { &raw mut (*ud.cast::<UdCell<T>>()).value }
+    }
+}
+
 /// Strongly typed full userdata.
 ///
 /// Note that the type that implement this trait **must** be registered with
@@ -70,3 +120,98 @@ pub trait TypedUd: Frame {
     fn set_uv(&mut self, n: NonZero<u16>) -> Option<UserFrame<Self>>;
     fn get_uv(&mut self, n: NonZero<u16>) -> Option<Value<Self>>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChunkType, Lua, PositiveInt};
+
+    struct Counter(i64);
+
+    impl UserType for Counter {
+        fn name() -> &'static CStr {
+            c"Counter"
+        }
+    }
+
+    struct Name(String);
+
+    impl UserType for Name {
+        fn name() -> &'static CStr {
+            c"Name"
+        }
+    }
+
+    #[test]
+    fn borrow() {
+        let mut lua = Lua::new(None).unwrap();
+
+        lua.require_base();
+        lua.require_string(true);
+        lua.register_ud::<Counter>();
+        lua.set_global(c"counter").push_ud(Counter(0));
+        lua.set_global(c"other").push_ud(Counter(2));
+        lua.set_global(c"add").push_fn(|cx| {
+            let mut a = cx.to_ud_mut::<Counter>(PositiveInt::ONE);
+            let b = cx.to_ud::<Counter>(PositiveInt::TWO).into_ud();
+
+            a.0 += b.0;
+
+            Ok(())
+        });
+
+        lua.set_global(c"inc").push_fn(|cx| {
+            cx.to_ud_mut::<Counter>(PositiveInt::ONE).0 += 1;
+            Ok(())
+        });
+
+        // The flag must be reset after an error.
+        let chunk = r#"
+            local ok, e = pcall(add, counter, counter)
+            assert(not ok and e:find("already mutably borrowed"), e)
+            inc(counter)
+            add(counter, other)
+        "#;
+
+        let f = lua.load(Some(c"=test"), ChunkType::Text, chunk).unwrap();
+
+        assert!(f.call().is_ok());
+    }
+
+    #[test]
+    fn take() {
+        let mut lua = Lua::new(None).unwrap();
+
+        lua.require_base();
+        lua.require_string(true);
+        lua.register_ud::<Name>();
+        lua.set_global(c"len").push_fn(|cx| {
+            let _ = cx.to_ud::<Name>(PositiveInt::ONE).into_ud().0.len();
+            Ok(())
+        });
+
+        // Access the value from Rust.
+        let mut g = lua.set_global(c"name");
+        let mut ud = g.push_ud(Name("foo".into()));
+
+        ud.get_mut().unwrap().0.push_str("bar");
+
+        assert_eq!(ud.get().unwrap().0, "foobar");
+        assert_eq!(ud.take().unwrap().0, "foobar");
+        assert!(ud.get().is_none());
+        assert!(ud.take().is_none());
+
+        drop(ud);
+        drop(g);
+
+        // Use from Lua.
+        let chunk = r#"
+            local ok, e = pcall(len, name)
+            assert(not ok and e:find("moved out"), e)
+        "#;
+
+        let f = lua.load(Some(c"=test"), ChunkType::Text, chunk).unwrap();
+
+        assert!(f.call().is_ok());
+    }
+}
@@ -1,4 +1,4 @@
-use super::{TypedUd, UserData, UserFrame, UserType};
+use super::{TAKEN, TypedUd, UdCell, UserData, UserFrame, UserType, is_boxed, ud_borrow, ud_value};
 use crate::ffi::{lua_State, zl_pop};
 use crate::state::RawState;
 use crate::{Frame, Unknown, Value};
@@ -11,14 +11,18 @@ use std::ops::DerefMut;
 /// Represents a full userdata on the top of stack.
 pub struct OwnedUd<'p, P: Frame, T> {
     parent: &'p mut P,
+    ud: *mut u8,
     phantom: PhantomData<T>,
 }
 
 impl<'p, P: Frame, T> OwnedUd<'p, P, T> {
+    /// # Safety
+    /// `ud` must be a full userdata for `T` on the top of stack.
     #[inline(always)]
-    pub(crate) unsafe fn new(parent: *mut P, _: *const T) -> Self {
+    pub(crate) unsafe fn new(parent: *mut P, ud: *mut u8) -> Self {
         Self {
             parent: unsafe { &mut *parent },
+            ud,
             phantom: PhantomData,
         }
     }
@@ -34,6 +38,56 @@ impl<'p, P: Frame, T> OwnedUd<'p, P, T> {
     }
 }
 
+impl<P: Frame, T: UserType> OwnedUd<'_, P, T> {
+    /// Returns [`None`] if the value is currently mutably borrowed (e.g. by a method that is
+    /// running) or has been moved out with [`Self::take()`].
+    #[inline(always)]
+    pub fn get(&self) -> Option<&T> {
+        match unsafe { ud_borrow(self.ud).get() } {
+            0.. => Some(unsafe { &*ud_value::<T>(self.ud) }),
+            _ => None,
+        }
+    }
+
+    /// Returns [`None`] if the value is currently borrowed (e.g. by a method that is running) or
+    /// has been moved out with [`Self::take()`].
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        match unsafe { ud_borrow(self.ud).get() } {
+            0 => Some(unsafe { &mut *ud_value::<T>(self.ud) }),
+            _ => None,
+        }
+    }
+
+    /// Move the value out of this userdata.
+    ///
+    /// The userdata will still alive on Lua side but any attempt to access the value (e.g. calling
+    /// its method) will raise a Lua error. Returns [`None`] under the same condition as
+    /// [`Self::get_mut()`].
+    pub fn take(&mut self) -> Option<T> {
+        let borrow = unsafe { ud_borrow(self.ud) };
+
+        if borrow.get() != 0 {
+            return None;
+        }
+
+        borrow.set(TAKEN);
+
+        // Our finalizer will not drop the value when the flag is TAKEN.
+        let v = if is_boxed::<T>() {
+            let ud = self.ud.cast::<UdCell<Box<T>>>();
+
+            *unsafe { std::ptr::read(&raw const (*ud).value) }
+        } else {
+            let ud = self.ud.cast::<UdCell<T>>();
+
+            unsafe { std::ptr::read(&raw const (*ud).value) }
+        };
+
+        Some(v)
+    }
+}
+
 impl<P: Frame, T> Drop for OwnedUd<'_, P, T> {
     #[inline(always)]
     fn drop(&mut self) {
@@ -1,4 +1,4 @@
-use super::{OwnedUd, UserType, is_boxed};
+use super::{OwnedUd, UserType};
 use crate::ffi::{lua_State, zl_getfield, zl_getmetatable, zl_pop, zl_touserdata};
 use crate::state::RawState;
 use crate::{Frame, TYPE_ID, Unknown};
@@ -37,15 +37,9 @@ impl<'p, P: Frame> UserData<'p, P> {
             return Err(self);
         }
 
-        // Get pointer to UD.
-        let ptr = unsafe { zl_touserdata(self.state(), -1).cast_const() };
-        let ptr = if is_boxed::<T>() {
-            unsafe { (*ptr.cast::<Box<T>>()).as_ref() }
-        } else {
-            ptr.cast::<T>()
-        };
+        let ud = unsafe { zl_touserdata(self.state(), -1) };
 
-        Ok(unsafe { OwnedUd::new(ManuallyDrop::new(self).deref_mut().0, ptr) })
+        Ok(unsafe { OwnedUd::new(ManuallyDrop::new(self).deref_mut().0, ud) })
     }
 
     #[inline(always)]
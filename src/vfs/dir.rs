@@ -0,0 +1,90 @@
+use super::{OpenMode, Vfs, VfsFile, VfsMetadata, normalize};
+use std::fs::OpenOptions;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// [`Vfs`] that confine all paths to a directory on the host filesystem.
+///
+/// `..` cannot go outside the directory and an absolute path is relative to the directory. Symbolic
+/// links are resolved and the path will be rejected if the target is outside the directory. Note
+/// that the check is done before the file is opened so it cannot prevent the other process from
+/// replacing the file with a symbolic link in between.
+pub struct DirFs {
+    root: PathBuf,
+}
+
+impl DirFs {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn resolve(&self, path: &Path) -> std::io::Result<PathBuf> {
+        let path = match normalize(path) {
+            Some(v) => self.root.join(v),
+            None => return Err(ErrorKind::PermissionDenied.into()),
+        };
+
+        // Resolve symbolic links to check if the target is still inside the root.
+        let root = self.root.canonicalize()?;
+        let path = match path.canonicalize() {
+            Ok(v) => v,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                // The file may be created so we need to check its parent instead. A dangling link
+                // also end up here, which can point to anywhere.
+                let (parent, name) = match (path.parent(), path.file_name()) {
+                    (Some(p), Some(n)) => (p, n),
+                    _ => return Err(e),
+                };
+
+                if path.symlink_metadata().is_ok() {
+                    return Err(ErrorKind::PermissionDenied.into());
+                }
+
+                parent.canonicalize()?.join(name)
+            }
+            Err(e) => return Err(e),
+        };
+
+        match path.starts_with(&root) {
+            true => Ok(path),
+            false => Err(ErrorKind::PermissionDenied.into()),
+        }
+    }
+}
+
+impl Vfs for DirFs {
+    fn open(&self, path: &Path, mode: &OpenMode) -> std::io::Result<Box<dyn VfsFile>> {
+        let f = OpenOptions::new()
+            .read(mode.read)
+            .write(mode.write)
+            .append(mode.append)
+            .truncate(mode.truncate)
+            .create(mode.create)
+            .open(self.resolve(path)?)?;
+
+        Ok(Box::new(f))
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        std::fs::read(self.resolve(path)?)
+    }
+
+    fn stat(&self, path: &Path) -> std::io::Result<VfsMetadata> {
+        let m = std::fs::metadata(self.resolve(path)?)?;
+
+        Ok(VfsMetadata {
+            is_dir: m.is_dir(),
+            len: m.len(),
+        })
+    }
+
+    fn list(&self, path: &Path) -> std::io::Result<Vec<String>> {
+        let mut names = Vec::new();
+
+        for e in std::fs::read_dir(self.resolve(path)?)? {
+            names.push(e?.file_name().to_string_lossy().into_owned());
+        }
+
+        Ok(names)
+    }
+}
@@ -0,0 +1,90 @@
+use super::{OpenMode, Vfs, VfsFile, VfsMetadata, normalize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Cursor, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Read-only [`Vfs`] that keep all files in memory (e.g. scripts embedded in the executable).
+#[derive(Default)]
+pub struct MemoryFs {
+    files: BTreeMap<PathBuf, Cow<'static, [u8]>>,
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file to this filesystem. This replace the file with the same path if any.
+    ///
+    /// # Panics
+    /// If `path` escape from the root.
+    pub fn insert(&mut self, path: impl AsRef<Path>, data: impl Into<Cow<'static, [u8]>>) {
+        let path = normalize(path.as_ref()).expect("path should not escape from the root");
+
+        self.files.insert(path, data.into());
+    }
+
+    fn get(&self, path: &Path) -> std::io::Result<&[u8]> {
+        normalize(path)
+            .and_then(|p| self.files.get(&p))
+            .map(|v| v.as_ref())
+            .ok_or_else(|| Error::from(ErrorKind::NotFound))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.files.keys().any(|k| k != path && k.starts_with(path))
+    }
+}
+
+impl Vfs for MemoryFs {
+    fn open(&self, path: &Path, mode: &OpenMode) -> std::io::Result<Box<dyn VfsFile>> {
+        if mode.is_write() {
+            return Err(ErrorKind::ReadOnlyFilesystem.into());
+        }
+
+        Ok(Box::new(Cursor::new(self.get(path)?.to_vec())))
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        self.get(path).map(|v| v.to_vec())
+    }
+
+    fn stat(&self, path: &Path) -> std::io::Result<VfsMetadata> {
+        if let Ok(v) = self.get(path) {
+            return Ok(VfsMetadata {
+                is_dir: false,
+                len: v.len().try_into().unwrap(),
+            });
+        }
+
+        match normalize(path) {
+            Some(p) if p.as_os_str().is_empty() || self.is_dir(&p) => Ok(VfsMetadata {
+                is_dir: true,
+                len: 0,
+            }),
+            _ => Err(ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn list(&self, path: &Path) -> std::io::Result<Vec<String>> {
+        let path = normalize(path).ok_or(ErrorKind::NotFound)?;
+        let mut names = BTreeSet::new();
+
+        for k in self.files.keys() {
+            if let Some(v) = k
+                .strip_prefix(&path)
+                .ok()
+                .and_then(|v| v.components().next())
+            {
+                names.insert(v.as_os_str().to_string_lossy().into_owned());
+            }
+        }
+
+        if names.is_empty() && !path.as_os_str().is_empty() {
+            return Err(ErrorKind::NotFound.into());
+        }
+
+        Ok(names.into_iter().collect())
+    }
+}
@@ -0,0 +1,483 @@
+pub use self::dir::*;
+pub use self::memory::*;
+pub use self::overlay::*;
+
+use crate::cache::load_cached;
+use crate::ffi::{
+    ZL_LOADED_TABLE, ZL_REGISTRYINDEX, zl_dofile, zl_getfield, zl_getsubtable, zl_gettop, zl_pop,
+    zl_pushvalue, zl_routeio, zl_setupvalue, zl_tolstring,
+};
+use crate::state::RawState;
+use crate::{ChunkType, Context, Error, Frame, Function, PositiveInt, Table, Type};
+use std::ffi::CString;
+use std::fmt::Write as _;
+use std::io::{Read, Seek, Write};
+use std::mem::ManuallyDrop;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+mod dir;
+mod memory;
+mod overlay;
+
+/// Virtual filesystem for Lua.
+///
+/// Use [`Lua::set_vfs()`](crate::Lua::set_vfs()) to route [`Frame::load_file()`], `loadfile`,
+/// `dofile`, `io.open`, `io.lines`, `io.input`, `io.output` and the Lua searcher of `require`
+/// through the implementation. `io.popen`, `package.loadlib` and the C searchers are disabled
+/// since they can only work with the real filesystem. See [`Frame::push_file()`] for the file
+/// returned from `io.open`.
+pub trait Vfs: 'static {
+    fn open(&self, path: &Path, mode: &OpenMode) -> std::io::Result<Box<dyn VfsFile>>;
+
+    /// Read the whole content of `path`.
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        let mut f = self.open(path, &OpenMode::READ)?;
+        let mut v = Vec::new();
+
+        f.read_to_end(&mut v)?;
+
+        Ok(v)
+    }
+
+    fn stat(&self, path: &Path) -> std::io::Result<VfsMetadata>;
+
+    /// Returns name of all entries in the directory `path`.
+    fn list(&self, path: &Path) -> std::io::Result<Vec<String>>;
+}
+
+/// File opened by [`Vfs`].
+pub trait VfsFile: Read + Write + Seek {}
+
+impl<T: Read + Write + Seek> VfsFile for T {}
+
+/// Options to open a file in [`Vfs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpenMode {
+    pub read: bool,
+    pub write: bool,
+    pub append: bool,
+    pub truncate: bool,
+    pub create: bool,
+}
+
+impl OpenMode {
+    pub const READ: Self = Self {
+        read: true,
+        write: false,
+        append: false,
+        truncate: false,
+        create: false,
+    };
+
+    /// Parse a mode for `io.open` (e.g. `r` or `w+b`).
+    pub fn parse(mode: &str) -> Option<Self> {
+        let mut i = mode.bytes();
+        let mut m = match i.next()? {
+            b'r' => Self::READ,
+            b'w' => Self {
+                read: false,
+                write: true,
+                append: false,
+                truncate: true,
+                create: true,
+            },
+            b'a' => Self {
+                read: false,
+                write: true,
+                append: true,
+                truncate: false,
+                create: true,
+            },
+            _ => return None,
+        };
+
+        // Check the rest.
+        let mut i = i.peekable();
+
+        if i.next_if_eq(&b'+').is_some() {
+            m.read = true;
+            m.write = true;
+        }
+
+        if i.all(|b| b == b'b') { Some(m) } else { None }
+    }
+
+    /// Returns `true` if the file need to be writable.
+    pub fn is_write(&self) -> bool {
+        self.write || self.append || self.truncate || self.create
+    }
+}
+
+/// Metadata of a file in [`Vfs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VfsMetadata {
+    pub is_dir: bool,
+    pub len: u64,
+}
+
+/// Returns a relative path of `path` with all `.` and `..` resolved or [`None`] if `path` escape
+/// from the root. Absolute path will be treated as relative to the root.
+pub(crate) fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut r = PathBuf::new();
+
+    for c in path.components() {
+        match c {
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+            Component::ParentDir => {
+                if !r.pop() {
+                    return None;
+                }
+            }
+            Component::Normal(v) => r.push(v),
+        }
+    }
+
+    Some(r)
+}
+
+/// Replace the loaders in the loaded libraries with the one that use `vfs`.
+pub(crate) fn install_vfs<P: Frame>(p: &mut P, vfs: &Rc<dyn Vfs>) {
+    unsafe { zl_getsubtable(p.state(), ZL_REGISTRYINDEX, ZL_LOADED_TABLE) };
+
+    // Base library.
+    if unsafe { zl_getfield(p.state(), -1, c"_G".as_ptr()) } == Type::Table {
+        let mut t = unsafe { Table::new(p) };
+        let v = vfs.clone();
+
+        t.set(c"loadfile")
+            .push_fn(move |cx| loadfile(cx, v.as_ref()));
+
+        let v = vfs.clone();
+
+        t.set(c"dofile").push_fn(move |cx| dofile(cx, v.as_ref()));
+    } else {
+        unsafe { zl_pop(p.state(), 1) };
+    }
+
+    // IO library. All functions that take a path are routed through the replaced io.open.
+    if unsafe { zl_getfield(p.state(), -1, c"io".as_ptr()) } == Type::Table {
+        let mut t = unsafe { Table::new(p) };
+        let v = vfs.clone();
+
+        t.set(c"open").push_fn(move |cx| open(cx, v.as_ref()));
+        t.set(c"popen").push_fn(|_| {
+            Err(Error::other(
+                c"'io.popen' is not allowed with a virtual filesystem",
+            ))
+        });
+
+        unsafe { zl_routeio(t.state()) };
+    } else {
+        unsafe { zl_pop(p.state(), 1) };
+    }
+
+    // Package library. The second searcher is the Lua searcher. The C searchers and loadlib are
+    // disabled since native libraries can only be loaded from the real filesystem.
+    if unsafe { zl_getfield(p.state(), -1, c"package".as_ptr()) } == Type::Table {
+        let mut t = unsafe { Table::new(p) };
+
+        t.set(c"loadlib").push_fn(|cx| {
+            cx.push_nil();
+            cx.push_str("dynamic libraries are disabled by the virtual filesystem");
+            cx.push_str("absent");
+            Ok(())
+        });
+
+        if unsafe { zl_getfield(t.state(), -1, c"searchers".as_ptr()) } == Type::Table {
+            let mut s = unsafe { Table::new(&mut t) };
+            let v = vfs.clone();
+
+            s.set(2).push_fn(move |cx| search(cx, v.as_ref()));
+
+            for i in [3, 4] {
+                s.set(i).push_fn(|cx| {
+                    cx.push_str("C loader is disabled by the virtual filesystem");
+                    Ok(())
+                });
+            }
+        } else {
+            unsafe { zl_pop(t.state(), 1) };
+        }
+    } else {
+        unsafe { zl_pop(p.state(), 1) };
+    }
+
+    unsafe { zl_pop(p.state(), 1) };
+}
+
+/// Load `path` from `vfs` as a Lua chunk.
+pub(crate) fn load<'a, 'b>(
+    cx: &'a mut Context<'b>,
+    vfs: &dyn Vfs,
+    path: &str,
+    ty: ChunkType,
+) -> Result<Function<'a, Context<'b>>, String> {
+    let data = match vfs.read(Path::new(path)) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("cannot open {path}: {e}")),
+    };
+
+    let name = match CString::new(format!("@{path}")) {
+        Ok(v) => v,
+        Err(_) => return Err(format!("cannot open {path}: path contains NUL")),
+    };
+
+    match load_cached(cx, &name, None, ty, &data) {
+        Ok(f) => Ok(f),
+        Err(mut e) => {
+            let m = String::from_utf8_lossy(e.to_bytes()).into_owned();
+            let mut e = ManuallyDrop::new(e);
+
+            unsafe { zl_pop(e.state(), 1) };
+
+            Err(m)
+        }
+    }
+}
+
+fn loadfile(cx: &mut Context, vfs: &dyn Vfs) -> Result<(), Error> {
+    let path = cx.to_str(PositiveInt::ONE);
+    let ty = match cx.try_str(PositiveInt::TWO) {
+        None | Some("bt") => ChunkType::Both,
+        Some("t") => ChunkType::Text,
+        Some("b") => ChunkType::Binary,
+        Some(_) => return Err(Error::arg(PositiveInt::TWO, c"invalid mode")),
+    };
+
+    // Load and set environment.
+    let env = cx.args() >= 3;
+    let e = match load(cx, vfs, path, ty) {
+        Ok(mut f) => {
+            if env {
+                unsafe { zl_pushvalue(f.state(), 3) };
+
+                if unsafe { zl_setupvalue(f.state(), -2, 1).is_null() } {
+                    unsafe { zl_pop(f.state(), 1) };
+                }
+            }
+
+            return Ok(());
+        }
+        Err(e) => e,
+    };
+
+    cx.push_nil();
+    cx.push_str(e);
+
+    Ok(())
+}
+
+fn dofile(cx: &mut Context, vfs: &dyn Vfs) -> Result<(), Error> {
+    let path = cx.to_str(PositiveInt::ONE);
+    let top = unsafe { zl_gettop(RawState::state(cx)) };
+
+    if top > 1 {
+        unsafe { zl_pop(RawState::state(cx), top - 1) };
+    }
+
+    // Load.
+    match load(cx, vfs, path, ChunkType::Both) {
+        Ok(f) => std::mem::forget(f),
+        Err(e) => return Err(Error::with_level(0, e)),
+    }
+
+    // Call with a continuation so the chunk can yield.
+    let n = unsafe { zl_dofile(RawState::state(cx)) };
+
+    if n > 0 {
+        unsafe { cx.release_values(n) };
+    }
+
+    Ok(())
+}
+
+fn open(cx: &mut Context, vfs: &dyn Vfs) -> Result<(), Error> {
+    let path = cx.to_str(PositiveInt::ONE);
+    let mode = cx.try_str(PositiveInt::TWO).unwrap_or("r");
+
+    let mode = match OpenMode::parse(mode) {
+        Some(v) => v,
+        None => return Err(Error::arg(PositiveInt::TWO, c"invalid mode")),
+    };
+
+    match vfs.open(Path::new(path), &mode) {
+        Ok(f) => drop(cx.push_file(f)),
+        Err(e) => {
+            cx.push_nil();
+            cx.push_str(format!("{path}: {e}"));
+        }
+    }
+
+    Ok(())
+}
+
+fn search(cx: &mut Context, vfs: &dyn Vfs) -> Result<(), Error> {
+    let name = cx.to_str(PositiveInt::ONE);
+    let path = match unsafe { package_path(RawState::state(cx)) } {
+        Some(v) => v,
+        None => return Err(Error::other(c"'package.path' must be a string")),
+    };
+
+    // Search.
+    let file = name.replace('.', "/");
+    let mut msg = String::new();
+
+    for t in path.split(';').filter(|v| !v.is_empty()) {
+        let p = t.replace('?', &file);
+
+        if !vfs.stat(Path::new(&p)).is_ok_and(|m| !m.is_dir) {
+            if !msg.is_empty() {
+                msg.push_str("\n\t");
+            }
+
+            write!(msg, "no file '{p}'").unwrap();
+            continue;
+        }
+
+        // Load.
+        match load(cx, vfs, &p, ChunkType::Both) {
+            Ok(f) => drop(f),
+            Err(e) => {
+                let m = format!("error loading module '{name}' from file '{p}':\n\t{e}");
+                return Err(Error::other(m));
+            }
+        }
+
+        cx.push_str(p);
+
+        return Ok(());
+    }
+
+    cx.push_str(msg);
+
+    Ok(())
+}
+
+unsafe fn package_path(s: *mut crate::ffi::lua_State) -> Option<String> {
+    unsafe { zl_getsubtable(s, ZL_REGISTRYINDEX, ZL_LOADED_TABLE) };
+
+    if unsafe { zl_getfield(s, -1, c"package".as_ptr()) } != Type::Table {
+        unsafe { zl_pop(s, 2) };
+        return None;
+    }
+
+    let path = match unsafe { zl_getfield(s, -1, c"path".as_ptr()) } {
+        Type::String => unsafe {
+            let mut len = 0;
+            let ptr = zl_tolstring(s, -1, &mut len);
+            let v = std::slice::from_raw_parts(ptr.cast(), len);
+
+            Some(String::from_utf8_lossy(v).into_owned())
+        },
+        _ => None,
+    };
+
+    unsafe { zl_pop(s, 3) };
+
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lua;
+
+    #[cfg(unix)]
+    #[test]
+    fn dir() {
+        use std::io::ErrorKind;
+        use std::os::unix::fs::symlink;
+
+        let base = std::env::temp_dir().join(format!("zl-vfs-{}", std::process::id()));
+        let root = base.join("root");
+        let outside = base.join("outside");
+
+        std::fs::create_dir_all(root.join("lib")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(root.join("lib/a.lua"), b"return 1").unwrap();
+        std::fs::write(outside.join("secret"), b"secret").unwrap();
+        symlink(&outside, root.join("out")).unwrap();
+        symlink(outside.join("secret"), root.join("secret")).unwrap();
+        symlink(outside.join("new"), root.join("new")).unwrap();
+        symlink(root.join("lib"), root.join("inner")).unwrap();
+
+        // Check the links.
+        let fs = DirFs::new(&root);
+        let denied =
+            |r: std::io::Result<Vec<u8>>| r.unwrap_err().kind() == ErrorKind::PermissionDenied;
+
+        assert_eq!(fs.read(Path::new("/lib/a.lua")).unwrap(), b"return 1");
+        assert_eq!(fs.read(Path::new("inner/a.lua")).unwrap(), b"return 1");
+        assert!(denied(fs.read(Path::new("out/secret"))));
+        assert!(denied(fs.read(Path::new("secret"))));
+        assert!(denied(fs.read(Path::new("../outside/secret"))));
+        assert_eq!(
+            fs.read(Path::new("lib/b.lua")).unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+
+        // Check the files that need to be created.
+        let mode = OpenMode::parse("w").unwrap();
+
+        assert!(fs.open(Path::new("lib/b.lua"), &mode).is_ok());
+        assert!(root.join("lib/b.lua").exists());
+        assert_eq!(
+            fs.open(Path::new("new"), &mode).err().unwrap().kind(),
+            ErrorKind::PermissionDenied
+        );
+        assert_eq!(
+            fs.open(Path::new("out/new"), &mode).err().unwrap().kind(),
+            ErrorKind::PermissionDenied
+        );
+        assert!(!outside.join("new").exists());
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn memory() {
+        let mut fs = MemoryFs::new();
+
+        fs.insert(
+            "main.lua",
+            b"assert(dofile('lib/a.lua') + require('lib.b') == 3)",
+        );
+        fs.insert("lib/a.lua", b"return 1");
+        fs.insert("lib/b.lua", b"return 2");
+        fs.insert("data.txt", b"hello");
+        fs.insert("yield.lua", b"coroutine.yield(1) return 2");
+
+        assert!(fs.stat(Path::new("lib")).unwrap().is_dir);
+        assert_eq!(
+            fs.list(Path::new("/")).unwrap(),
+            ["data.txt", "lib", "main.lua", "yield.lua"]
+        );
+        assert!(normalize(Path::new("lib/../../main.lua")).is_none());
+
+        // Run.
+        let mut lua = Lua::builder().all_libs().vfs(fs).build().unwrap();
+        let f = lua.load_file("main.lua", ChunkType::Text).unwrap().unwrap();
+
+        assert!(f.call().is_ok());
+
+        // Check io.open and loadfile.
+        let chunk = "assert(io.open('data.txt'):read('a') == 'hello')\n\
+            assert(io.open('/lib/../data.txt'))\n\
+            assert(not io.open('data.txt', 'w'))\n\
+            assert(not io.open('../data.txt'))\n\
+            assert(loadfile('lib/a.lua')() == 1)\n\
+            assert(not loadfile('foo.lua'))\n\
+            for l in io.lines('data.txt') do assert(l == 'hello') end\n\
+            assert(io.input('data.txt') == io.input())\n\
+            assert(io.read('a') == 'hello')\n\
+            assert(not pcall(io.lines, 'foo.txt'))\n\
+            assert(not pcall(io.popen, 'ls'))\n\
+            assert(select(3, package.loadlib('foo.so', 'bar')) == 'absent')\n\
+            local co = coroutine.wrap(function() return dofile('yield.lua') end)\n\
+            assert(co() == 1 and co() == 2)";
+        let f = lua.load(None, ChunkType::Text, chunk).unwrap();
+
+        assert!(f.call().is_ok());
+    }
+}
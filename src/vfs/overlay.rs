@@ -0,0 +1,72 @@
+use super::{OpenMode, Vfs, VfsFile, VfsMetadata};
+use std::collections::BTreeSet;
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// [`Vfs`] that stack multiple filesystems on top of each other.
+///
+/// A file will be looked up from the first layer to the last one. Opening a file for writing
+/// always use the first layer.
+pub struct OverlayFs {
+    layers: Vec<Box<dyn Vfs>>,
+}
+
+impl OverlayFs {
+    pub fn new(layers: Vec<Box<dyn Vfs>>) -> Self {
+        Self { layers }
+    }
+
+    fn find<T>(&self, mut f: impl FnMut(&dyn Vfs) -> std::io::Result<T>) -> std::io::Result<T> {
+        for l in &self.layers {
+            match f(l.as_ref()) {
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                r => return r,
+            }
+        }
+
+        Err(ErrorKind::NotFound.into())
+    }
+}
+
+impl Vfs for OverlayFs {
+    fn open(&self, path: &Path, mode: &OpenMode) -> std::io::Result<Box<dyn VfsFile>> {
+        if mode.is_write() {
+            match self.layers.first() {
+                Some(l) => l.open(path, mode),
+                None => Err(ErrorKind::ReadOnlyFilesystem.into()),
+            }
+        } else {
+            self.find(|l| l.open(path, mode))
+        }
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        self.find(|l| l.read(path))
+    }
+
+    fn stat(&self, path: &Path) -> std::io::Result<VfsMetadata> {
+        self.find(|l| l.stat(path))
+    }
+
+    fn list(&self, path: &Path) -> std::io::Result<Vec<String>> {
+        let mut names = BTreeSet::new();
+        let mut found = false;
+
+        for l in &self.layers {
+            match l.list(path) {
+                Ok(v) => {
+                    names.extend(v);
+                    found = true;
+                }
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        if !found {
+            return Err(ErrorKind::NotFound.into());
+        }
+
+        Ok(names.into_iter().collect())
+    }
+}
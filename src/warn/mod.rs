@@ -0,0 +1,54 @@
+use crate::state::ExtraData;
+use std::ffi::{CStr, c_char, c_int, c_void};
+
+/// Type of Rust function to receive Lua warnings.
+///
+/// The message is a complete warning with all of its pieces concatenated. Control messages (`@on`
+/// and `@off`) are handled by Zero Lua so the handler will never receive it.
+pub type WarnHandler = dyn Fn(&str);
+
+/// [`WarnHandler`] that forward Lua warnings to [`log::warn!()`] with `lua` as a target.
+#[cfg(feature = "log")]
+pub fn log_warn(msg: &str) {
+    log::warn!(target: "lua", "{msg}");
+}
+
+/// Implementation of `lua_WarnFunction`. `ud` must be a pointer to [`ExtraData`].
+pub(crate) unsafe extern "C-unwind" fn warn(ud: *mut c_void, msg: *const c_char, tocont: c_int) {
+    let ex = unsafe { &*ud.cast::<ExtraData>() };
+    let msg = unsafe { CStr::from_ptr(msg).to_bytes() };
+    let mut buf = ex.warn_buf.borrow_mut();
+
+    // Check if control message, which is a single piece message that start with @.
+    if buf.is_empty() && tocont == 0 && msg.first() == Some(&b'@') {
+        match msg {
+            b"@on" => ex.warn_on.set(true),
+            b"@off" => ex.warn_on.set(false),
+            _ => {}
+        }
+
+        return;
+    }
+
+    buf.extend_from_slice(msg);
+
+    if tocont != 0 {
+        return;
+    }
+
+    // Invoke handler.
+    let msg = std::mem::take(&mut *buf);
+
+    drop(buf);
+
+    if !ex.warn_on.get() {
+        return;
+    }
+
+    let msg = String::from_utf8_lossy(&msg);
+
+    match ex.warn.borrow().as_deref() {
+        Some(f) => f(&msg),
+        None => eprintln!("Lua warning: {msg}"),
+    }
+}
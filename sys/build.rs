@@ -51,6 +51,16 @@ fn main() {
         cc.flag("-xc++");
     }
 
+    // Include our configurations. We can't use define() here since MSVC does not support
+    // function-like macro on the command line.
+    let conf = format!("{root}{MAIN_SEPARATOR_STR}zlconf.h");
+
+    if cc.get_compiler().is_like_msvc() {
+        cc.flag(format!("/FI{conf}"));
+    } else {
+        cc.flag("-include").flag(&conf);
+    }
+
     match os.as_str() {
         "linux" => cc.define("LUA_USE_LINUX", None),
         "macos" => cc.define("LUA_USE_MACOSX", None),
@@ -62,6 +72,9 @@ fn main() {
         cc.file(format!("{lua}{MAIN_SEPARATOR_STR}{src}"));
     }
 
+    cc.include(lua);
+    cc.file("zlsys.cpp");
+
     cc.compile("lua");
 
     // Export include path.
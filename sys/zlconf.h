@@ -0,0 +1,16 @@
+/* This file will be included in all Lua sources. */
+#ifndef ZLCONF_H
+#define ZLCONF_H
+
+/*
+** Use the seed for string hashing passed to zlsys_newstate so it can be fixed in deterministic
+** mode. The states created by the other functions get the same seed as stock Lua. See zlsys.cpp
+** for the implementation.
+*/
+struct lua_State;
+
+unsigned int zlsys_makeseed(struct lua_State *L);
+
+#define luai_makeseed(L) zlsys_makeseed(L)
+
+#endif
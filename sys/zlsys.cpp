@@ -0,0 +1,43 @@
+#include "lstring.h"
+
+#include <string.h>
+#include <time.h>
+
+// Seed for the state being created by zlsys_newstate on the current thread.
+static thread_local const unsigned int *seed;
+
+extern "C" lua_State *zlsys_newstate(lua_Alloc f, void *ud, unsigned int v)
+{
+    // lua_newstate never throw since it catch all errors itself.
+    seed = &v;
+
+    auto L = lua_newstate(f, ud);
+
+    seed = nullptr;
+
+    return L;
+}
+
+unsigned int zlsys_makeseed(lua_State *L)
+{
+    if (seed) {
+        return *seed;
+    }
+
+    // Same as luai_makeseed on lstate.c.
+    char buff[3 * sizeof(size_t)];
+    unsigned int h = cast_uint(time(NULL));
+    int p = 0;
+
+#define addbuff(b,p,e) \
+  { size_t t = cast_sizet(e); \
+    memcpy(b + p, &t, sizeof(t)); p += sizeof(t); }
+
+    addbuff(buff, p, L);
+    addbuff(buff, p, &h);
+    addbuff(buff, p, &lua_newstate);
+
+#undef addbuff
+
+    return luaS_hash(buff, p, h);
+}
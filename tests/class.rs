@@ -0,0 +1,63 @@
+use zl::{ChunkType, Context, Error, Frame, IntoLua, Lua, PositiveInt, class};
+
+struct Counter {
+    value: i64,
+}
+
+#[class(global)]
+impl Counter {
+    #[class]
+    fn new(cx: &mut Context) -> Result<(), Error> {
+        cx.push_ud(Self { value: 0 });
+        Ok(())
+    }
+
+    #[prop]
+    fn value(&self, cx: &mut Context) -> Result<(), Error> {
+        self.value.into_lua(cx);
+        Ok(())
+    }
+
+    fn incr(&mut self, _: &mut Context) -> Result<(), Error> {
+        self.value += 1;
+        Ok(())
+    }
+
+    fn add(&mut self, cx: &mut Context) -> Result<(), Error> {
+        let other = cx.to_ud::<Self>(PositiveInt::TWO);
+
+        self.value += other.into_ud().value;
+
+        Ok(())
+    }
+}
+
+#[test]
+fn class() {
+    let mut lua = Lua::new(None).unwrap();
+
+    lua.require_base();
+    lua.register_ud::<Counter>();
+
+    let code = "local a, b = Counter.new(), Counter.new()\n\
+        a:incr()\n\
+        a:incr()\n\
+        b:add(a)\n\
+        assert(a.value == 2 and b.value == 2)\n\
+        local ok, e = pcall(function() a:add(a) end)\n\
+        assert(not ok)\n\
+        assert(a.value == 2)\n\
+        a:incr()\n\
+        assert(a.value == 3)\n\
+        error(e, 0)";
+    let f = lua.load(Some(c"=class"), ChunkType::Text, code).unwrap();
+    let mut e = match f.call() {
+        Ok(_) => panic!("the chunk should raise an error"),
+        Err(e) => e,
+    };
+
+    assert_eq!(
+        e.to_str().unwrap(),
+        "class:6: bad argument #1 to 'add' (Counter is already mutably borrowed)"
+    );
+}
@@ -0,0 +1,3 @@
+local name = ...
+
+return 'hello ' .. name
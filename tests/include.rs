@@ -0,0 +1,17 @@
+use zl::{ChunkType, Frame, Lua, include_lua};
+
+#[test]
+fn include() {
+    let mut lua = Lua::new(None).unwrap();
+    let src = include_lua!("fixtures/include.lua");
+
+    assert_eq!(src.name(), c"@fixtures/include.lua");
+
+    let mut f = lua.load(Some(src.name()), ChunkType::Text, src).unwrap();
+
+    f.push_str("zl");
+
+    let mut r = f.call().ok().unwrap();
+
+    assert_eq!(r.to_bytes(1), Some(b"hello zl".as_slice()));
+}
@@ -0,0 +1,83 @@
+use zl::{Async, ChunkType, Context, Error, Frame, Lua, Yieldable, module};
+
+struct Greeter;
+
+#[module(name = "greeter")]
+impl Greeter {
+    pub const NAME: &str = "greeter";
+    pub const VERSION: i64 = 2;
+    pub const RATIO: f64 = 0.5;
+    const SECRET: &str = "secret";
+
+    pub fn hello(cx: &mut Context) -> Result<(), Error> {
+        cx.push_str(Self::greeting());
+        Ok(())
+    }
+
+    pub async fn later(cx: &mut Context<'_, Yieldable>) -> Result<(), Error> {
+        cx.push_str("later");
+        Ok(())
+    }
+
+    fn greeting() -> &'static str {
+        match Self::SECRET.is_empty() {
+            true => "",
+            false => "hello",
+        }
+    }
+}
+
+#[module(name = "game.util", global)]
+mod util {
+    use zl::{Context, Error, Frame};
+
+    pub const LIMIT: i32 = 10;
+    const HIDDEN: bool = true;
+
+    pub fn name(cx: &mut Context) -> Result<(), Error> {
+        cx.push_str(if HIDDEN { "util" } else { "" });
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    fn private(_: &mut Context) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[test]
+fn module() {
+    let mut lua = Lua::new(None).unwrap();
+
+    lua.require_base();
+    lua.require_package(true, false);
+
+    assert!(Greeter::register(&mut lua));
+    assert!(!Greeter::register(&mut lua));
+    assert!(util::register(&mut lua));
+
+    let code = "local g = require('greeter')\n\
+        assert(g.NAME == 'greeter')\n\
+        assert(g.VERSION == 2 and g.RATIO == 0.5)\n\
+        assert(g.hello() == 'hello')\n\
+        assert(type(g.later) == 'function')\n\
+        assert(g.SECRET == nil and g.greeting == nil)\n\
+        local u = require('game.util')\n\
+        assert(u == game.util)\n\
+        assert(u.LIMIT == 10 and u.name() == 'util')\n\
+        assert(u.HIDDEN == nil and u.private == nil)\n\
+        return g.later()";
+    let mut lua = lua.into_async().spawn();
+    let f = lua.load(None, ChunkType::Text, code).unwrap();
+
+    pollster::block_on(async {
+        let mut f = f.into_async();
+        let mut r = match f.resume().await {
+            Ok(Async::Finish(v)) => v,
+            Ok(Async::Yield(_)) => panic!("unexpected yield"),
+            Err(mut e) => panic!("{}", e.to_str().unwrap()),
+        };
+
+        assert_eq!(r.to_bytes(1), Some(b"later".as_slice()));
+    });
+}
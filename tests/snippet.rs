@@ -0,0 +1,70 @@
+use zl::{ChunkType, Frame, Lua, SafeOs, SnippetError, lua};
+
+#[test]
+fn snippet() {
+    let mut lua = Lua::new(None).unwrap();
+
+    lua.require_base();
+    let name = "zl";
+
+    // Captures, concatenation and inequality.
+    let v: (String, bool) = lua!(&mut lua, return "hello " .. $name, $name ~= "zl").unwrap();
+
+    assert_eq!(v, ("hello zl".into(), false));
+
+    // The same variable can be captured more than once.
+    let v: String = lua!(&mut lua, return $name .. $name).unwrap();
+
+    assert_eq!(v, "zlzl");
+
+    // Error should report the line in this file.
+    let line = line!() + 3;
+    let v: Result<(), _> = lua!(&mut lua,
+        local v = $name
+        error("oops " .. v)
+    );
+
+    assert_eq!(
+        v,
+        Err(SnippetError::Run(format!(
+            "tests/snippet.rs:{line}: oops zl"
+        )))
+    );
+}
+
+#[test]
+fn state() {
+    let mut lua = Lua::new(None).unwrap();
+    let h = lua.interrupt_handle();
+
+    // The interrupt flag should be cleared after the snippet returns.
+    h.interrupt();
+
+    let v: Result<(), _> = lua!(&mut lua, while true do end);
+
+    assert_eq!(v, Err(SnippetError::Run("interrupted".into())));
+
+    let v: i64 = lua!(&mut lua, return 1).unwrap();
+
+    assert_eq!(v, 1);
+
+    let f = lua.load(None, ChunkType::Text, "return 1").unwrap();
+
+    assert!(f.call().is_ok());
+
+    // Check os.exit.
+    lua.require_safe_os(true, SafeOs::new());
+
+    let v: Result<(), _> = lua!(&mut lua, os.exit(3));
+
+    assert_eq!(
+        v,
+        Err(SnippetError::Run("exit requested with code 3".into()))
+    );
+    assert_eq!(lua.take_exit_code(), Some(3));
+
+    let v: i64 = lua!(&mut lua, return 2).unwrap();
+
+    assert_eq!(v, 2);
+    assert_eq!(lua.take_exit_code(), None);
+}